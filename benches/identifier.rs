@@ -0,0 +1,36 @@
+//! Benchmark for parsing identifiers.
+//! Run with `cargo bench`.
+
+use std::hint::black_box;
+
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup, Criterion,
+    Throughput,
+};
+use specmc_base::parse::{Identifier, Parse};
+
+const IDENTIFIERS: usize = 100_000;
+
+fn parse_identifiers(c: &mut Criterion) {
+    let tokens: Vec<String> = (0..IDENTIFIERS)
+        .map(|i| format!("identifier_{i}"))
+        .collect();
+
+    let mut group: BenchmarkGroup<WallTime> = c.benchmark_group("identifier");
+    group.throughput(Throughput::Elements(IDENTIFIERS as u64));
+    group.bench_function("parse", |b| {
+        b.iter_batched_ref(
+            || tokens.clone(),
+            |tokens: &mut Vec<String>| {
+                while !tokens.is_empty() {
+                    black_box(Identifier::parse(black_box(tokens)).unwrap());
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_identifiers);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Benchmark for tokenizing, counting allocations as well as time.
+//! Run with `cargo bench`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion, Throughput,
+};
+use specmc_base::tokenize::{tokenize, tokenize_borrowed};
+
+/// The system allocator, counting how many allocations are made.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const STATEMENTS: usize = 20_000;
+
+/// Count the allocations made by one call of `f`.
+fn allocations<T>(f: impl Fn() -> T) -> usize {
+    let before: usize = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn tokenize_input(c: &mut Criterion) {
+    let input: String = (0..STATEMENTS)
+        .map(|i| format!("let value_{i} = \"string {i}\" + {i}.5; // comment\n"))
+        .collect();
+    let tokens: usize = tokenize_borrowed(&input).len();
+
+    // Criterion only measures time, so the allocations are counted separately.
+    println!("tokenize {tokens} tokens:");
+    println!(
+        "  owned:    {} allocations",
+        allocations(|| tokenize(black_box(&input)))
+    );
+    println!(
+        "  borrowed: {} allocations",
+        allocations(|| tokenize_borrowed(black_box(&input)))
+    );
+
+    let mut group: BenchmarkGroup<WallTime> = c.benchmark_group("tokenize");
+    group.throughput(Throughput::Elements(tokens as u64));
+    group.bench_function("owned", |b| b.iter(|| tokenize(black_box(&input))));
+    group.bench_function("borrowed", |b| {
+        b.iter(|| tokenize_borrowed(black_box(&input)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tokenize_input);
+criterion_main!(benches);
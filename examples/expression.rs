@@ -0,0 +1,65 @@
+use specmc_base::{
+    ensure_tokens,
+    parse::{parse_binary_left, Literal, Parse, ParseError},
+    tokenize::tokenize,
+};
+
+/// An arithmetic expression over literals.
+#[derive(Debug)]
+enum Expression {
+    Literal(Literal),
+    Binary(Box<Expression>, String, Box<Expression>),
+}
+impl Expression {
+    fn binary(left: Expression, operator: &str, right: Expression) -> Expression {
+        Expression::Binary(Box::new(left), operator.to_string(), Box::new(right))
+    }
+
+    fn evaluate(&self) -> Result<Literal, ParseError> {
+        match self {
+            Expression::Literal(literal) => Ok(literal.clone()),
+            Expression::Binary(left, operator, right) => {
+                let (left, right) = (left.evaluate()?, right.evaluate()?);
+                match operator.as_str() {
+                    "+" => left.add(&right),
+                    "-" => left.sub(&right),
+                    "*" => left.mul(&right),
+                    _ => left.div(&right),
+                }
+            }
+        }
+    }
+}
+
+/// The lowest precedence level: `+` and `-`.
+fn sum(tokens: &mut Vec<String>) -> Result<Expression, ParseError> {
+    parse_binary_left(tokens, product, &["+", "-"], Expression::binary)
+}
+
+/// `*` and `/`, which bind tighter than `+` and `-`.
+/// `/` isn't a special token, so it has to be written with spaces around it.
+fn product(tokens: &mut Vec<String>) -> Result<Expression, ParseError> {
+    parse_binary_left(tokens, atom, &["*", "/"], Expression::binary)
+}
+
+/// A literal, or a parenthesized expression.
+fn atom(tokens: &mut Vec<String>) -> Result<Expression, ParseError> {
+    if tokens.last().is_some_and(|token| token == "(") {
+        tokens.pop();
+        let expression: Expression = sum(tokens)?;
+        ensure_tokens!(tokens, ")");
+        Ok(expression)
+    } else {
+        Literal::parse(tokens).map(Expression::Literal)
+    }
+}
+
+fn main() -> Result<(), ParseError> {
+    let mut tokens: Vec<String> = tokenize("1 + 2*3 - (4 - 1) / 2");
+    tokens.reverse();
+
+    let expression: Expression = sum(&mut tokens)?;
+    println!("{expression:?}");
+    println!("= {}", expression.evaluate()?);
+    Ok(())
+}
@@ -0,0 +1,267 @@
+//! Module for parsing expressions with operator precedence.
+
+use std::fmt::Display;
+
+use crate::{
+    ensure_tokens,
+    parse::{parse_nested, Parse, ParseError},
+};
+
+/// How a chain of operators with the same precedence is grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ** b ** c` is `a ** (b ** c)`.
+    Right,
+}
+
+/// An expression tree, with atoms of type `T`.
+/// Parentheses only group, so they aren't kept in the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression<T> {
+    Atom(T),
+    Prefix {
+        operator: String,
+        operand: Box<Expression<T>>,
+    },
+    Binary {
+        left: Box<Expression<T>>,
+        operator: String,
+        right: Box<Expression<T>>,
+    },
+}
+impl<T> Expression<T> {
+    /// Reduce the expression to a single value, from the atoms up.
+    /// This evaluates the expression without matching on the tree by hand.
+    pub fn fold<R>(
+        &self,
+        atom: &impl Fn(&T) -> R,
+        prefix: &impl Fn(&str, R) -> R,
+        binary: &impl Fn(R, &str, R) -> R,
+    ) -> R {
+        match self {
+            Expression::Atom(value) => atom(value),
+            Expression::Prefix { operator, operand } => {
+                prefix(operator, operand.fold(atom, prefix, binary))
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left: R = left.fold(atom, prefix, binary);
+                binary(left, operator, right.fold(atom, prefix, binary))
+            }
+        }
+    }
+}
+/// Binary expressions are always parenthesized, so the output parses back into the same tree
+/// regardless of precedence.
+impl<T: Display> Display for Expression<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Atom(value) => write!(f, "{value}"),
+            Expression::Prefix { operator, operand } => write!(f, "{operator}{operand}"),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => write!(f, "({left} {operator} {right})"),
+        }
+    }
+}
+
+/// A table of prefix and binary operators, for parsing [`Expression`]s.
+/// Higher precedences bind tighter. Atoms are parsed with `T::parse`, and `(` and `)` group.
+///
+/// ```
+/// use specmc_base::{
+///     expression::{Associativity, Expression, OperatorTable},
+///     parse::Literal,
+///     tokenize,
+/// };
+///
+/// let table: OperatorTable = OperatorTable::new()
+///     .binary("+", 1, Associativity::Left)
+///     .binary("*", 2, Associativity::Left)
+///     .prefix("-", 3);
+///
+/// let mut tokens: Vec<String> = tokenize!("-1 + 2 * (3 + 4)");
+/// let expression: Expression<Literal> = table.parse(&mut tokens).unwrap();
+/// assert_eq!(expression.to_string(), "(-1 + (2 * (3 + 4)))");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperatorTable {
+    prefix: Vec<(String, u8)>,
+    binary: Vec<(String, u8, Associativity)>,
+}
+impl OperatorTable {
+    /// Create a table without any operators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a prefix operator, like `-` in `-x`.
+    /// The operand is parsed at the operator's precedence, so `-a * b` is `(-a) * b`
+    /// when `-` has a higher precedence than `*`.
+    pub fn prefix(mut self, operator: impl Into<String>, precedence: u8) -> Self {
+        self.prefix.push((operator.into(), precedence));
+        self
+    }
+
+    /// Add a binary operator, like `+` in `a + b`.
+    pub fn binary(
+        mut self,
+        operator: impl Into<String>,
+        precedence: u8,
+        associativity: Associativity,
+    ) -> Self {
+        self.binary
+            .push((operator.into(), precedence, associativity));
+        self
+    }
+
+    /// Parse an expression. Parsing stops at the first token after an operand
+    /// that isn't a binary operator, which is not consumed.
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    pub fn parse<T: Parse>(&self, tokens: &mut Vec<String>) -> Result<Expression<T>, ParseError> {
+        self.parse_from(tokens, 0)
+    }
+
+    /// Parse an expression whose binary operators all have at least the given precedence.
+    fn parse_from<T: Parse>(
+        &self,
+        tokens: &mut Vec<String>,
+        min_precedence: u8,
+    ) -> Result<Expression<T>, ParseError> {
+        let mut left: Expression<T> = self.parse_operand(tokens)?;
+
+        while let Some((operator, precedence, associativity)) = tokens.last().and_then(|token| {
+            self.binary.iter().find(|(operator, precedence, _)| {
+                operator == token && *precedence >= min_precedence
+            })
+        }) {
+            tokens.pop();
+            let next: u8 = match associativity {
+                Associativity::Left => precedence.saturating_add(1),
+                Associativity::Right => *precedence,
+            };
+            let right: Expression<T> =
+                parse_nested(tokens, |tokens| self.parse_from(tokens, next))?;
+            left = Expression::Binary {
+                left: Box::new(left),
+                operator: operator.clone(),
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse a prefix expression, a parenthesized expression, or an atom.
+    fn parse_operand<T: Parse>(
+        &self,
+        tokens: &mut Vec<String>,
+    ) -> Result<Expression<T>, ParseError> {
+        let token: &String = tokens
+            .last()
+            .ok_or_else(|| ParseError::end_of_file("expression"))?;
+        if token == "(" {
+            tokens.pop();
+            let expression: Expression<T> =
+                parse_nested(tokens, |tokens| self.parse_from(tokens, 0))?;
+            ensure_tokens!(tokens, ")");
+            return Ok(expression);
+        }
+
+        match self.prefix.iter().find(|(operator, _)| operator == token) {
+            Some((operator, precedence)) => {
+                tokens.pop();
+                let operand: Expression<T> =
+                    parse_nested(tokens, |tokens| self.parse_from(tokens, *precedence))?;
+                Ok(Expression::Prefix {
+                    operator: operator.clone(),
+                    operand: Box::new(operand),
+                })
+            }
+            None => T::parse(tokens).map(Expression::Atom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse::{Identifier, Literal},
+        tokenize, tokens,
+    };
+
+    use super::*;
+
+    fn table() -> OperatorTable {
+        OperatorTable::new()
+            .binary("||", 1, Associativity::Left)
+            .binary("==", 2, Associativity::Left)
+            .binary("+", 3, Associativity::Left)
+            .binary("-", 3, Associativity::Left)
+            .binary("*", 4, Associativity::Left)
+            .binary("**", 6, Associativity::Right)
+            .prefix("-", 5)
+            .prefix("!", 5)
+    }
+
+    fn parse(input: &str) -> Result<String, ParseError> {
+        let mut tokens: Vec<String> = tokenize!(input);
+        let expression: Expression<Identifier> = table().parse(&mut tokens)?;
+        Ok(expression.to_string())
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(parse("a"), Ok("a".to_string()));
+        assert_eq!(parse("a + b * c"), Ok("(a + (b * c))".to_string()));
+        assert_eq!(parse("a * b + c"), Ok("((a * b) + c)".to_string()));
+        assert_eq!(parse("a - b - c"), Ok("((a - b) - c)".to_string()));
+        assert_eq!(parse("a ** b ** c"), Ok("(a ** (b ** c))".to_string()));
+        assert_eq!(parse("a == b || !c"), Ok("((a == b) || !c)".to_string()));
+    }
+
+    #[test]
+    fn test_prefix_and_grouping() {
+        assert_eq!(parse("-a * b"), Ok("(-a * b)".to_string()));
+        assert_eq!(parse("-a ** b"), Ok("-(a ** b)".to_string()));
+        assert_eq!(parse("!!a"), Ok("!!a".to_string()));
+        assert_eq!(parse("(a + b) * c"), Ok("((a + b) * c)".to_string()));
+        assert_eq!(parse("-(a + b)"), Ok("-(a + b)".to_string()));
+
+        let displayed: String = parse("a - -(b * c) ** d").unwrap();
+        assert_eq!(parse(&displayed), Ok(displayed.clone()));
+    }
+
+    #[test]
+    fn test_stops_and_errors() {
+        let mut tokens: Vec<String> = tokenize!("a + b; c");
+        assert!(table().parse::<Identifier>(&mut tokens).is_ok());
+        assert_eq!(tokens, tokens![";", "c"]);
+
+        assert_eq!(parse("a +"), Err(ParseError::end_of_file("expression")));
+        assert_eq!(parse("(a + b"), Err(ParseError::end_of_file(")")));
+        assert_eq!(parse("(a b)"), Err(ParseError::expected("b", ")")));
+    }
+
+    #[test]
+    fn test_fold() {
+        let mut tokens: Vec<String> = tokenize!("2 * -3 + 10");
+        let expression: Expression<Literal> = table().parse(&mut tokens).unwrap();
+        let value: Literal = expression.fold(
+            &|literal: &Literal| literal.clone(),
+            &|_, operand: Literal| Literal::Integer(0).sub(&operand).unwrap(),
+            &|left: Literal, operator, right: Literal| match operator {
+                "+" => left.add(&right).unwrap(),
+                _ => left.mul(&right).unwrap(),
+            },
+        );
+        assert_eq!(value, Literal::Integer(4));
+    }
+}
@@ -0,0 +1,100 @@
+//! Module for interning strings, so repeated names share one allocation.
+
+use std::{collections::HashMap, rc::Rc};
+
+/// A handle to a string in an [`Interner`].
+/// Symbols from the same interner are equal exactly when their strings are equal,
+/// so comparing them is an integer comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+impl Symbol {
+    /// The index of the symbol in its interner.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A set of interned strings.
+/// Each distinct string is stored once, and is identified by a [`Symbol`].
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+impl Interner {
+    /// Create an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the symbol for a string, adding the string if it isn't interned yet.
+    ///
+    /// # Panics
+    /// Panics if more than `u32::MAX` distinct strings are interned.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return *symbol;
+        }
+        let symbol: Symbol = Symbol(u32::try_from(self.strings.len()).expect("Too many symbols"));
+        let string: Rc<str> = Rc::from(string);
+        self.strings.push(string.clone());
+        self.symbols.insert(string, symbol);
+        symbol
+    }
+
+    /// Get the symbol for a string, if it is interned.
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).copied()
+    }
+
+    /// The string of a symbol.
+    ///
+    /// # Panics
+    /// Panics if the symbol is from a different interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+
+    /// The shared string of a symbol.
+    ///
+    /// # Panics
+    /// Panics if the symbol is from a different interner.
+    pub fn resolve_rc(&self, symbol: Symbol) -> Rc<str> {
+        self.strings[symbol.index()].clone()
+    }
+
+    /// The number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether no strings are interned.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_interner() {
+        let mut interner: Interner = Interner::new();
+        assert!(interner.is_empty());
+
+        let a: Symbol = interner.intern("a");
+        let b: Symbol = interner.intern("b");
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("a"), a);
+        assert_eq!(interner.len(), 2);
+
+        assert_eq!(interner.resolve(a), "a");
+        assert_eq!(interner.resolve(b), "b");
+        assert_eq!(interner.get("b"), Some(b));
+        assert_eq!(interner.get("c"), None);
+        assert!(Rc::ptr_eq(&interner.resolve_rc(a), &interner.resolve_rc(a)));
+    }
+}
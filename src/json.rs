@@ -0,0 +1,346 @@
+//! Module for parsing JSON, like the text components in Minecraft data packs.
+
+use std::fmt::Display;
+
+use crate::{
+    ensure,
+    parse::{parse_colon, parse_delimited_list, parse_nested, Parse, ParseError},
+};
+
+/// A JSON value.
+///
+/// Numbers are stored as `f64`, and support fractions and exponents, like `-1.5e3`.
+/// Objects keep the order their members were written in. Duplicate keys are kept too,
+/// and [`Json::get`] finds the last one, like most JSON parsers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    /// Get the value of an object member.
+    /// Returns `None` if the key is missing or this is not an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(other, _)| other == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Boolean(value) => write!(f, "{value}"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => write!(f, "\"{}\"", escape_json(value)),
+            Json::Array(values) => {
+                let values: Vec<String> = values.iter().map(Json::to_string).collect();
+                write!(f, "[{}]", values.join(","))
+            }
+            Json::Object(members) => {
+                let members: Vec<String> = members
+                    .iter()
+                    .map(|(key, value)| format!("\"{}\":{value}", escape_json(key)))
+                    .collect();
+                write!(f, "{{{}}}", members.join(","))
+            }
+        }
+    }
+}
+impl Parse for Json {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: &String = tokens
+            .last()
+            .ok_or_else(|| ParseError::end_of_file("value"))?;
+        match token.as_str() {
+            "[" => parse_nested(tokens, |tokens| {
+                parse_delimited_list(tokens, "[", ",", "]", false).map(Json::Array)
+            }),
+            "{" => parse_nested(tokens, |tokens| {
+                parse_delimited_list(tokens, "{", ",", "}", false).map(|members| {
+                    Json::Object(
+                        members
+                            .into_iter()
+                            .map(|Member(key, value)| (key, value))
+                            .collect(),
+                    )
+                })
+            }),
+            _ => {
+                let token: String = tokens.pop().unwrap();
+                match token.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Boolean(true)),
+                    "false" => Ok(Json::Boolean(false)),
+                    _ if token.starts_with('"') => unescape_json(&token)
+                        .map(Json::String)
+                        .map_err(|error| ParseError::invalid(token, error)),
+                    "-" => {
+                        let number: String = tokens
+                            .pop()
+                            .ok_or_else(|| ParseError::end_of_file("number"))?;
+                        parse_number(&format!("-{number}")).map(Json::Number)
+                    }
+                    _ => parse_number(&token).map(Json::Number),
+                }
+            }
+        }
+    }
+}
+
+/// A `"key": value` member of an object.
+struct Member(String, Json);
+impl Parse for Member {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let key: String = tokens.pop().ok_or_else(|| ParseError::end_of_file("key"))?;
+        ensure!(
+            key.starts_with('"'),
+            ParseError::invalid(key, "Object keys must be strings")
+        );
+        let key: String = unescape_json(&key).map_err(|error| ParseError::invalid(key, error))?;
+
+        // `:` isn't a special token, so it can be glued to the value, like in `"a":1`.
+        parse_colon(tokens)?;
+        Ok(Member(key, Json::parse(tokens)?))
+    }
+}
+
+/// Parse a JSON number: an optional `-`, digits without leading zeros,
+/// an optional fraction, and an optional exponent.
+fn parse_number(token: &str) -> Result<f64, ParseError> {
+    let unsigned: &str = token.strip_prefix('-').unwrap_or(token);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    let is_digits = |digits: &str| !digits.is_empty() && digits.bytes().all(|c| c.is_ascii_digit());
+    let valid: bool = is_digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(is_digits)
+        && exponent.is_none_or(|exponent| {
+            is_digits(exponent.strip_prefix(['-', '+']).unwrap_or(exponent))
+        });
+    ensure!(valid, ParseError::invalid(token, "Invalid JSON value"));
+
+    let number: f64 = token.parse().unwrap();
+    ensure!(
+        number.is_finite(),
+        ParseError::invalid(token, "Number out of range")
+    );
+    Ok(number)
+}
+
+/// Decode a JSON string, including its quotes.
+fn unescape_json(literal: &str) -> Result<String, String> {
+    let mut string: String = String::new();
+    let mut chars = literal.chars().skip(1);
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Ok(string),
+            '\\' => {}
+            c => {
+                string.push(c);
+                continue;
+            }
+        }
+
+        string.push(match chars.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{C}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let first: u16 = code_unit(&mut chars)?;
+                let mut units: Vec<u16> = vec![first];
+                // Characters outside the basic plane are written as a surrogate pair,
+                // like `\uD83D\uDE00`.
+                if (0xD800..0xDC00).contains(&first) {
+                    ensure!(
+                        chars.next() == Some('\\') && chars.next() == Some('u'),
+                        "Unpaired surrogate in string".to_string()
+                    );
+                    units.push(code_unit(&mut chars)?);
+                }
+                match char::decode_utf16(units).collect::<Result<Vec<char>, _>>() {
+                    Ok(decoded) if decoded.len() == 1 => decoded[0],
+                    _ => return Err("Unpaired surrogate in string".to_string()),
+                }
+            }
+            Some(escape) => return Err(format!("Unknown escape `\\{escape}` in string")),
+            None => break,
+        });
+    }
+
+    Err("Unterminated string".to_string())
+}
+
+/// Read the four hex digits of a `\u` escape.
+fn code_unit(chars: &mut impl Iterator<Item = char>) -> Result<u16, String> {
+    let digits: String = chars.take(4).collect();
+    ensure!(
+        digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit()),
+        "Escape `\\u` must be followed by exactly four hex digits".to_string()
+    );
+    Ok(u16::from_str_radix(&digits, 16).unwrap())
+}
+
+/// Escape a string so it can be written back as a JSON string.
+fn escape_json(string: &str) -> String {
+    let mut escaped: String = String::new();
+    for c in string.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            c if c.is_control() => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_parse, test_parse_exact, tokenize};
+
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        test_parse_exact!("null", Json, Ok(Json::Null));
+        test_parse_exact!("true", Json, Ok(Json::Boolean(true)));
+        test_parse_exact!("[]", Json, Ok(Json::Array(vec![])));
+        test_parse_exact!("{}", Json, Ok(Json::Object(vec![])));
+
+        let json: Json = Json::parse_str(
+            r#"{"text": "Hi \u00a7a", "extra": [{"bold":true}, -1.5e3, 0, 2E+2], "a":null}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            json,
+            Json::Object(vec![
+                ("text".to_string(), Json::String("Hi §a".to_string())),
+                (
+                    "extra".to_string(),
+                    Json::Array(vec![
+                        Json::Object(vec![("bold".to_string(), Json::Boolean(true))]),
+                        Json::Number(-1500.0),
+                        Json::Number(0.0),
+                        Json::Number(200.0),
+                    ])
+                ),
+                ("a".to_string(), Json::Null),
+            ])
+        );
+        assert_eq!(json.get("a"), Some(&Json::Null));
+        assert_eq!(json.get("b"), None);
+
+        let displayed: String = json.to_string();
+        assert_eq!(
+            displayed,
+            r#"{"text":"Hi §a","extra":[{"bold":true},-1500,0,200],"a":null}"#
+        );
+        assert_eq!(Json::parse_str(&displayed), Ok(json));
+    }
+
+    #[test]
+    fn test_json_strings() {
+        test_parse_exact!(
+            r#""\"\\\/\b\f\n\r\t""#,
+            Json,
+            Ok(Json::String("\"\\/\u{8}\u{C}\n\r\t".to_string()))
+        );
+        test_parse_exact!(
+            r#""\uD83D\uDE00""#,
+            Json,
+            Ok(Json::String("😀".to_string()))
+        );
+        assert_eq!(
+            Json::String("a\"\u{1}".to_string()).to_string(),
+            r#""a\"\u0001""#
+        );
+
+        let mut tokens: Vec<String> = tokenize!(r#""\uD83D" "\u12" "\q""#);
+        test_parse!(
+            tokens,
+            Json,
+            Err(ParseError::invalid(
+                r#""\uD83D""#,
+                "Unpaired surrogate in string"
+            ))
+        );
+        test_parse!(
+            tokens,
+            Json,
+            Err(ParseError::invalid(
+                r#""\u12""#,
+                "Escape `\\u` must be followed by exactly four hex digits"
+            ))
+        );
+        test_parse!(
+            tokens,
+            Json,
+            Err(ParseError::invalid(
+                r#""\q""#,
+                "Unknown escape `\\q` in string"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_json_errors() {
+        let mut tokens: Vec<String> = tokenize!("01 1. .5 1e 0x1 +1 1e400 undefined");
+        for token in ["01", "1.", ".5", "1e", "0x1", "+"] {
+            test_parse!(
+                tokens,
+                Json,
+                Err(ParseError::invalid(token, "Invalid JSON value"))
+            );
+        }
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Json,
+            Err(ParseError::invalid("1e400", "Number out of range"))
+        );
+        test_parse!(
+            tokens,
+            Json,
+            Err(ParseError::invalid("undefined", "Invalid JSON value"))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("{a: 1}");
+        test_parse!(
+            tokens,
+            Json,
+            Err(ParseError::invalid("a:", "Object keys must be strings"))
+        );
+        let mut tokens: Vec<String> = tokenize!(r#"{"a" 1}"#);
+        test_parse!(tokens, Json, Err(ParseError::expected("1", ":")));
+        let mut tokens: Vec<String> = tokenize!("[1, 2,]");
+        assert!(Json::parse(&mut tokens).is_err());
+        let mut tokens: Vec<String> = tokenize!(r#"{"a": [1"#);
+        test_parse!(tokens, Json, Err(ParseError::end_of_file(", or ]")));
+    }
+}
@@ -1,6 +1,13 @@
 //! A library with common code for parsing Minecraft specification.
 
+pub mod expression;
+pub mod intern;
+pub mod json;
+pub mod minecraft;
+pub mod nbt;
 pub mod parse;
+pub mod source_map;
+pub mod stream;
 pub mod tokenize;
 
 /// Ensure that the given condition is true, otherwise return the given value.
@@ -21,4 +28,13 @@ mod tests {
             assert_eq!(<$ty>::parse(&mut $tokens), $value);
         };
     }
+
+    #[macro_export]
+    macro_rules! test_parse_exact {
+        ($input:expr, $ty:ty, $value:expr) => {
+            let mut tokens: Vec<String> = $crate::tokenize!($input);
+            assert_eq!(<$ty>::parse(&mut tokens), $value);
+            assert!(tokens.is_empty(), "Unconsumed tokens: {tokens:?}");
+        };
+    }
 }
@@ -0,0 +1,900 @@
+//! Module for parsing Minecraft-specific syntax.
+
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    ensure, ensure_tokens,
+    parse::{parse_str_with, peek_n, Identifier, KeyValue, Literal, Parse, ParseError},
+    tokenize::Token,
+};
+
+/// A color code, like `#FF8800`.
+/// The color must be a `#` followed by exactly 6 hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+impl Color {
+    /// The color packed into a `u32`, as `0xRRGGBB`.
+    pub fn to_u32(self) -> u32 {
+        (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+}
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+impl Parse for Color {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        ensure_tokens!(tokens, "#");
+        let value: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("color"))?;
+
+        if let Some(digit) = value.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(ParseError::invalid(
+                value,
+                format!("Invalid hex digit `{digit}` in color code"),
+            ));
+        }
+        ensure!(
+            value.len() == 6,
+            ParseError::invalid(value, "Color codes must have exactly 6 hex digits")
+        );
+
+        let channel = |i: usize| u8::from_str_radix(&value[i..i + 2], 16).unwrap();
+        Ok(Color {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+        })
+    }
+}
+
+/// The valid target selector variables.
+const SELECTOR_VARIABLES: &[char] = &['p', 'a', 'r', 's', 'e', 'n'];
+
+/// A target selector, like `@a` or `@e[type=zombie,limit=1]`.
+/// The variable must be one of `p`, `a`, `r`, `s`, `e`, or `n`.
+/// Argument values are kept as raw text, and may contain nested brackets and strings.
+/// Brackets in values must be balanced, and arguments must be separated by `,`.
+/// A trailing `,` before the `]` is allowed, like in Minecraft.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Selector {
+    pub variable: char,
+    pub arguments: Vec<(Identifier, String)>,
+}
+impl Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}", self.variable)?;
+        if !self.arguments.is_empty() {
+            let arguments: Vec<String> = self
+                .arguments
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            write!(f, "[{}]", arguments.join(","))?;
+        }
+        Ok(())
+    }
+}
+impl Parse for Selector {
+    /// Token lists don't keep the whitespace between tokens, so argument values parsed here are
+    /// their tokens joined together. [`Parse::parse_str`] keeps values as written in the source.
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        parse_selector(tokens, |_, _| None)
+    }
+
+    fn parse_str(input: &str) -> Result<Self, ParseError> {
+        parse_str_with(input, |tokens, source| {
+            parse_selector(tokens, |before, after| {
+                let first: &Token = &source[source.len() - before];
+                let last: &Token = &source[source.len() - after - 1];
+                input
+                    .get(first.span.start..last.span.end)
+                    .map(str::to_string)
+            })
+        })
+    }
+}
+
+/// Parse a selector. `raw` is given how many tokens were left before and after an argument value,
+/// and returns the value's source text, if it is known.
+fn parse_selector(
+    tokens: &mut Vec<String>,
+    raw: impl Fn(usize, usize) -> Option<String>,
+) -> Result<Selector, ParseError> {
+    ensure_tokens!(tokens, "@");
+    let token: String = tokens
+        .pop()
+        .ok_or_else(|| ParseError::end_of_file("selector"))?;
+    let mut chars: std::str::Chars = token.chars();
+    let variable: char = match (chars.next(), chars.next()) {
+        (Some(variable), None) if SELECTOR_VARIABLES.contains(&variable) => variable,
+        _ => {
+            return Err(ParseError::invalid(
+                token,
+                "Selector variable must be one of p, a, r, s, e, or n",
+            ))
+        }
+    };
+
+    let mut arguments: Vec<(Identifier, String)> = vec![];
+    if tokens.last().is_some_and(|token| token == "[") {
+        tokens.pop();
+        while tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+            let key: Identifier = Identifier::parse(tokens)?;
+            ensure_tokens!(tokens, "=");
+            let before: usize = tokens.len();
+            let value: String = parse_selector_value(tokens)?;
+            arguments.push((key, raw(before, tokens.len()).unwrap_or(value)));
+
+            if tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+                ensure_tokens!(tokens, ",");
+            }
+        }
+        ensure_tokens!(tokens, "]");
+    }
+
+    Ok(Selector {
+        variable,
+        arguments,
+    })
+}
+
+/// Collect the tokens of a selector argument value, up to the next top-level `,` or `]`,
+/// or the key of a next argument, like `limit` in `type=zombie limit=1`.
+/// Brackets in the value must be balanced.
+fn parse_selector_value(tokens: &mut Vec<String>) -> Result<String, ParseError> {
+    let mut value: String = String::new();
+    let mut closers: Vec<&str> = vec![];
+
+    loop {
+        let token: &String = tokens
+            .last()
+            .ok_or_else(|| ParseError::end_of_file(closers.last().unwrap_or(&"]")))?;
+        match token.as_str() {
+            "," | "]" if closers.is_empty() => break,
+            // The key of the next argument, after a missing `,`.
+            _ if closers.is_empty()
+                && !value.is_empty()
+                && peek_n(tokens, 1).is_some_and(|token| token == "=") =>
+            {
+                break
+            }
+            "[" => closers.push("]"),
+            "{" => closers.push("}"),
+            "(" => closers.push(")"),
+            "]" | "}" | ")" => match closers.pop() {
+                Some(closer) => ensure!(token == closer, ParseError::expected(token, closer)),
+                None => {
+                    return Err(ParseError::invalid(
+                        token,
+                        format!("Unmatched `{token}` in selector argument value"),
+                    ))
+                }
+            },
+            _ => {}
+        }
+        value += &tokens.pop().unwrap();
+    }
+
+    ensure!(
+        !value.is_empty(),
+        ParseError::invalid(tokens.last().unwrap(), "Expected selector argument value")
+    );
+    Ok(value)
+}
+
+/// A bracketed list of properties, like the block state `[facing=north,half=top]`.
+/// Properties keep the order they were written in, and duplicate keys are an error.
+/// Like in Minecraft, a trailing comma, as in `[age=1,]`, is an error.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PropertyMap<V>(pub Vec<KeyValue<Identifier, V>>);
+impl<V> PropertyMap<V> {
+    /// Get the value of a property.
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.0
+            .iter()
+            .find(|property| property.key.0 == key)
+            .map(|property| &property.value)
+    }
+}
+impl<V: Display> Display for PropertyMap<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let properties: Vec<String> = self.0.iter().map(KeyValue::to_string).collect();
+        write!(f, "[{}]", properties.join(","))
+    }
+}
+impl<V: Parse> Parse for PropertyMap<V> {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        ensure_tokens!(tokens, "[");
+
+        let mut properties: Vec<KeyValue<Identifier, V>> = vec![];
+        while tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+            let property: KeyValue<Identifier, V> = KeyValue::parse(tokens)?;
+            ensure!(
+                properties.iter().all(|other| other.key != property.key),
+                ParseError::invalid(
+                    property.key.0.clone(),
+                    format!("Duplicate property `{}`", property.key)
+                )
+            );
+            properties.push(property);
+
+            if tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+                ensure_tokens!(tokens, ",");
+                ensure!(
+                    tokens.last().is_none_or(|token| token != "]"),
+                    ParseError::invalid("]", "Trailing , is not allowed before ]")
+                );
+            }
+        }
+        ensure_tokens!(tokens, "]");
+
+        Ok(PropertyMap(properties))
+    }
+}
+
+/// A single coordinate, like `5`, `~`, `~-2`, or `^1.5`.
+/// `~` is relative to the current position and `^` is local to the current rotation.
+/// A bare `~` or `^` has an offset of zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coordinate {
+    Absolute(f64),
+    Relative(f64),
+    Local(f64),
+}
+impl Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (prefix, offset) = match self {
+            Coordinate::Absolute(value) => return write!(f, "{value}"),
+            Coordinate::Relative(offset) => ("~", offset),
+            Coordinate::Local(offset) => ("^", offset),
+        };
+        if *offset == 0.0 {
+            write!(f, "{prefix}")
+        } else {
+            write!(f, "{prefix}{offset}")
+        }
+    }
+}
+impl Parse for Coordinate {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: &String = tokens
+            .last()
+            .ok_or_else(|| ParseError::end_of_file("coordinate"))?;
+        let coordinate: fn(f64) -> Coordinate = match token.chars().next() {
+            Some('~') => Coordinate::Relative,
+            Some('^') => Coordinate::Local,
+            _ => {
+                return match Literal::parse(tokens)? {
+                    Literal::Integer(value) => Ok(Coordinate::Absolute(value as f64)),
+                    Literal::Float(value) => Ok(Coordinate::Absolute(value)),
+                    literal => Err(ParseError::invalid(
+                        literal.to_string(),
+                        "Expected coordinate",
+                    )),
+                };
+            }
+        };
+
+        let token: String = tokens.pop().unwrap();
+        let offset: &str = &token[1..];
+        if offset.is_empty() {
+            return Ok(coordinate(0.0));
+        }
+        match offset.parse::<f64>() {
+            Ok(offset) => Ok(coordinate(offset)),
+            Err(_) => Err(ParseError::invalid(token, "Invalid coordinate offset")),
+        }
+    }
+}
+
+/// The number of ticks in a second.
+pub const TICKS_PER_SECOND: u64 = 20;
+/// The number of ticks in a real-time minute.
+pub const TICKS_PER_MINUTE: u64 = 60 * TICKS_PER_SECOND;
+/// The number of ticks in an in-game day.
+pub const TICKS_PER_DAY: u64 = 24000;
+
+/// A duration in game ticks, like `20t`, `5s`, `2m`, `0.5d`, or `20`.
+/// The unit can be `t` for ticks, `s` for seconds, `m` for minutes, or `d` for in-game days.
+/// Numbers without a unit are ticks. Fractional values are allowed as long as they make
+/// a whole number of ticks, so `0.5s` is 10 ticks, but `0.01s` is an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TickDuration(pub u64);
+impl Display for TickDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}t", self.0)
+    }
+}
+impl Parse for TickDuration {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("duration"))?;
+        let (number, unit) = match token.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&token[..i], Some(c)),
+            _ => (token.as_str(), None),
+        };
+        let value: f64 = match number.parse::<f64>() {
+            Ok(value) if number.starts_with(|c: char| c.is_ascii_digit() || c == '.') => value,
+            _ => return Err(ParseError::invalid(token, "Invalid duration")),
+        };
+        let ticks_per_unit: u64 = match unit {
+            None | Some('t') => 1,
+            Some('s') => TICKS_PER_SECOND,
+            Some('m') => TICKS_PER_MINUTE,
+            Some('d') => TICKS_PER_DAY,
+            Some(unit) => {
+                return Err(ParseError::invalid(
+                    token,
+                    format!("Unknown time unit `{unit}`, expected t, s, m, or d"),
+                ))
+            }
+        };
+
+        let ticks: f64 = value * ticks_per_unit as f64;
+        ensure!(
+            ticks.fract() == 0.0,
+            ParseError::invalid(token, "Durations must be a whole number of ticks")
+        );
+        // `u64::MAX as f64` rounds up to 2^64, which doesn't fit, so it is excluded.
+        if ticks >= u64::MAX as f64 {
+            return Err(ParseError::invalid(token, "Duration out of range"));
+        }
+        Ok(TickDuration(ticks as u64))
+    }
+}
+
+/// A version number, like `1.20` or `1.20.4`.
+/// Versions compare component by component, so `1.9 < 1.20`.
+/// Missing components count as zero, so `1.20 == 1.20.0`, but they are displayed as written.
+#[derive(Debug, Clone)]
+pub struct Version(pub Vec<u32>);
+impl Version {
+    /// The components without trailing zeros, which compare the same as the zero-padded ones.
+    fn significant(&self) -> &[u32] {
+        let len: usize = self
+            .0
+            .iter()
+            .rposition(|&component| component != 0)
+            .map_or(0, |i| i + 1);
+        &self.0[..len]
+    }
+}
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.significant() == other.significant()
+    }
+}
+impl Eq for Version {}
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.significant().hash(state);
+    }
+}
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.significant().cmp(other.significant())
+    }
+}
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        write!(f, "{}", components.join("."))
+    }
+}
+impl Parse for Version {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("version"))?;
+        let components: Option<Vec<u32>> = token
+            .split('.')
+            .map(|component| {
+                component
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+                    .then(|| component.parse().ok())
+                    .flatten()
+            })
+            .collect();
+
+        match components {
+            Some(components) => Ok(Version(components)),
+            None => Err(ParseError::invalid(
+                token,
+                "Versions must be numbers separated by dots",
+            )),
+        }
+    }
+}
+
+/// A value with an optional minimum version, like `@since(1.20) value`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Versioned<T> {
+    pub since: Option<Version>,
+    pub value: T,
+}
+impl<T> Versioned<T> {
+    /// Whether the value is available in the given version.
+    /// Values without a minimum version are always available.
+    pub fn is_available_in(&self, version: &Version) -> bool {
+        self.since.as_ref().is_none_or(|since| since <= version)
+    }
+}
+impl<T: Display> Display for Versioned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(since) = &self.since {
+            write!(f, "@since({since}) ")?;
+        }
+        write!(f, "{}", self.value)
+    }
+}
+impl<T: Parse> Parse for Versioned<T> {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let mut since: Option<Version> = None;
+        if tokens.last().is_some_and(|token| token == "@")
+            && peek_n(tokens, 1).is_some_and(|token| token == "since")
+        {
+            tokens.truncate(tokens.len() - 2);
+            ensure_tokens!(tokens, "(");
+            since = Some(Version::parse(tokens)?);
+            ensure_tokens!(tokens, ")");
+        }
+
+        Ok(Versioned {
+            since,
+            value: T::parse(tokens)?,
+        })
+    }
+}
+
+/// A resource location, like `minecraft:stone` or `stone`.
+/// The namespace defaults to `minecraft` when it is left out.
+///
+/// `:` is not a special character, so a resource location is a single token.
+/// Namespaces can contain lowercase letters, digits, and `_`, and paths can also contain `/`.
+/// `.` and `-` are special characters, so they can't be used here.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceLocation {
+    pub namespace: String,
+    pub path: String,
+}
+impl Display for ResourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+impl Parse for ResourceLocation {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("resource location"))?;
+        let (namespace, path) = token.split_once(':').unwrap_or(("minecraft", &token));
+
+        let is_valid = |part: &str, extra: &[char]| {
+            !part.is_empty()
+                && part.chars().all(|c| {
+                    c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || extra.contains(&c)
+                })
+        };
+        ensure!(
+            is_valid(namespace, &[]),
+            ParseError::invalid(
+                &token,
+                "Namespaces can only contain lowercase letters, digits, and underscores"
+            )
+        );
+        ensure!(
+            is_valid(path, &['/']),
+            ParseError::invalid(
+                &token,
+                "Resource paths can only contain lowercase letters, digits, underscores, and slashes"
+            )
+        );
+
+        Ok(ResourceLocation {
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{test_parse, tokenize};
+
+    use super::*;
+
+    #[test]
+    fn test_color() {
+        let mut tokens: Vec<String> = tokenize!("#FF8800 #00ff7f");
+
+        let color: Color = Color::parse(&mut tokens).unwrap();
+        assert_eq!(
+            color,
+            Color {
+                r: 0xFF,
+                g: 0x88,
+                b: 0x00
+            }
+        );
+        assert_eq!(color.to_u32(), 0xFF8800);
+        assert_eq!(color.to_string(), "#FF8800");
+        test_parse!(
+            tokens,
+            Color,
+            Ok(Color {
+                r: 0x00,
+                g: 0xFF,
+                b: 0x7F
+            })
+        );
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("#FF88 #FFGG00 FF8800");
+        test_parse!(
+            tokens,
+            Color,
+            Err(ParseError::InvalidToken {
+                token: "FF88".to_string(),
+                error: "Color codes must have exactly 6 hex digits".to_string(),
+                expected: None
+            })
+        );
+        test_parse!(
+            tokens,
+            Color,
+            Err(ParseError::InvalidToken {
+                token: "FFGG00".to_string(),
+                error: "Invalid hex digit `G` in color code".to_string(),
+                expected: None
+            })
+        );
+        test_parse!(
+            tokens,
+            Color,
+            Err(ParseError::InvalidToken {
+                token: "FF8800".to_string(),
+                error: "Expected #".to_string(),
+                expected: Some("#".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn test_selector() {
+        let mut tokens: Vec<String> = tokenize!(
+            "@a @e[type=zombie, limit=1] @e[name=\"Big Steve\",scores={kills=1..,deaths=..5},nbt={Tags:[a,b]}]"
+        );
+
+        test_parse!(
+            tokens,
+            Selector,
+            Ok(Selector {
+                variable: 'a',
+                arguments: vec![]
+            })
+        );
+
+        let selector: Selector = Selector::parse(&mut tokens).unwrap();
+        assert_eq!(
+            selector,
+            Selector {
+                variable: 'e',
+                arguments: vec![
+                    (Identifier("type".to_string()), "zombie".to_string()),
+                    (Identifier("limit".to_string()), "1".to_string())
+                ]
+            }
+        );
+        assert_eq!(selector.to_string(), "@e[type=zombie,limit=1]");
+
+        test_parse!(
+            tokens,
+            Selector,
+            Ok(Selector {
+                variable: 'e',
+                arguments: vec![
+                    (Identifier("name".to_string()), "\"Big Steve\"".to_string()),
+                    (
+                        Identifier("scores".to_string()),
+                        "{kills=1..,deaths=..5}".to_string()
+                    ),
+                    (Identifier("nbt".to_string()), "{Tags:[a,b]}".to_string())
+                ]
+            })
+        );
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("@x @e[type=] @e[type=zombie");
+        test_parse!(
+            tokens,
+            Selector,
+            Err(ParseError::invalid(
+                "x",
+                "Selector variable must be one of p, a, r, s, e, or n"
+            ))
+        );
+        test_parse!(
+            tokens,
+            Selector,
+            Err(ParseError::invalid("]", "Expected selector argument value"))
+        );
+        tokens.pop();
+        test_parse!(tokens, Selector, Err(ParseError::end_of_file("]")));
+
+        let mut tokens: Vec<String> = tokenize!("@e[type=zombie limit=1]");
+        test_parse!(tokens, Selector, Err(ParseError::expected("limit", ",")));
+
+        let mut tokens: Vec<String> = tokenize!("@e[nbt={a:[1}] @e[nbt=1}] @e[nbt={a:1]");
+        test_parse!(tokens, Selector, Err(ParseError::expected("}", "]")));
+        tokens.truncate(tokens.len() - 2);
+        test_parse!(
+            tokens,
+            Selector,
+            Err(ParseError::invalid(
+                "}",
+                "Unmatched `}` in selector argument value"
+            ))
+        );
+        tokens.truncate(tokens.len() - 2);
+        test_parse!(tokens, Selector, Err(ParseError::expected("]", "}")));
+
+        let selector: Selector =
+            Selector::parse_str("@e[nbt={a: [1, 2]}, name=\"Big  Steve\", type=zombie,]").unwrap();
+        assert_eq!(
+            selector.arguments,
+            vec![
+                (Identifier("nbt".to_string()), "{a: [1, 2]}".to_string()),
+                (Identifier("name".to_string()), "\"Big  Steve\"".to_string()),
+                (Identifier("type".to_string()), "zombie".to_string())
+            ]
+        );
+        assert!(Selector::parse_str("@e[type=zombie limit=1]")
+            .unwrap_err()
+            .eq_ignoring_span(&ParseError::expected("limit", ",")));
+    }
+
+    #[test]
+    fn test_property_map() {
+        let mut tokens: Vec<String> = tokenize!("[facing=north,half=top] []");
+
+        let state: PropertyMap<Identifier> = PropertyMap::parse(&mut tokens).unwrap();
+        assert_eq!(state.get("facing"), Some(&Identifier("north".to_string())));
+        assert_eq!(state.get("half"), Some(&Identifier("top".to_string())));
+        assert_eq!(state.get("waterlogged"), None);
+        assert_eq!(state.to_string(), "[facing=north,half=top]");
+        test_parse!(tokens, PropertyMap<Identifier>, Ok(PropertyMap(vec![])));
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("[age=1,age=2]");
+        test_parse!(
+            tokens,
+            PropertyMap<crate::parse::Literal>,
+            Err(ParseError::invalid("age", "Duplicate property `age`"))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("[age=1 lit=true]");
+        test_parse!(
+            tokens,
+            PropertyMap<crate::parse::Literal>,
+            Err(ParseError::expected("lit", ","))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("[age=1,]");
+        test_parse!(
+            tokens,
+            PropertyMap<crate::parse::Literal>,
+            Err(ParseError::invalid(
+                "]",
+                "Trailing , is not allowed before ]"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_version() {
+        let mut tokens: Vec<String> = tokenize!("1.20.4 1 1.x 1..2");
+
+        let version: Version = Version::parse(&mut tokens).unwrap();
+        assert_eq!(version, Version(vec![1, 20, 4]));
+        assert_eq!(version.to_string(), "1.20.4");
+        test_parse!(tokens, Version, Ok(Version(vec![1])));
+        test_parse!(
+            tokens,
+            Version,
+            Err(ParseError::invalid(
+                "1.x",
+                "Versions must be numbers separated by dots"
+            ))
+        );
+        test_parse!(
+            tokens,
+            Version,
+            Err(ParseError::invalid(
+                "1..2",
+                "Versions must be numbers separated by dots"
+            ))
+        );
+
+        assert!(Version(vec![1, 9]) < Version(vec![1, 20]));
+        assert_eq!(Version(vec![1, 20]), Version(vec![1, 20, 0]));
+        assert_eq!(
+            Version(vec![1, 20]).cmp(&Version(vec![1, 20, 0])),
+            Ordering::Equal
+        );
+        assert!(Version(vec![1, 20]) < Version(vec![1, 20, 0, 1]));
+        assert!(Version(vec![1, 20, 0]) < Version(vec![1, 20, 1]));
+        assert_eq!(Version(vec![1, 20, 0]).to_string(), "1.20.0");
+
+        let versions: HashSet<Version> =
+            HashSet::from([Version(vec![1, 20]), Version(vec![1, 20, 0])]);
+        assert_eq!(versions.len(), 1);
+    }
+
+    #[test]
+    fn test_versioned() {
+        let mut tokens: Vec<String> = tokenize!("@since(1.20) #FF8800 #000000 @since(x) #000000");
+
+        let versioned: Versioned<Color> = Versioned::parse(&mut tokens).unwrap();
+        assert_eq!(versioned.since, Some(Version(vec![1, 20])));
+        assert_eq!(versioned.to_string(), "@since(1.20) #FF8800");
+        assert!(versioned.is_available_in(&Version(vec![1, 20, 1])));
+        assert!(!versioned.is_available_in(&Version(vec![1, 19])));
+
+        let versioned: Versioned<Color> = Versioned::parse(&mut tokens).unwrap();
+        assert_eq!(versioned.since, None);
+        assert_eq!(versioned.to_string(), "#000000");
+        assert!(versioned.is_available_in(&Version(vec![1, 0])));
+
+        test_parse!(
+            tokens,
+            Versioned<Color>,
+            Err(ParseError::invalid(
+                "x",
+                "Versions must be numbers separated by dots"
+            ))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("@a");
+        test_parse!(
+            tokens,
+            Versioned<Selector>,
+            Ok(Versioned {
+                since: None,
+                value: Selector {
+                    variable: 'a',
+                    arguments: vec![]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_coordinate() {
+        let mut tokens: Vec<String> = tokenize!("~ ~1 ~-2 ^ ^ ^5 -3 1.5 ^.5");
+
+        let coordinate: Coordinate = Coordinate::parse(&mut tokens).unwrap();
+        assert_eq!(coordinate, Coordinate::Relative(0.0));
+        assert_eq!(coordinate.to_string(), "~");
+        test_parse!(tokens, Coordinate, Ok(Coordinate::Relative(1.0)));
+        let coordinate: Coordinate = Coordinate::parse(&mut tokens).unwrap();
+        assert_eq!(coordinate, Coordinate::Relative(-2.0));
+        assert_eq!(coordinate.to_string(), "~-2");
+        test_parse!(tokens, Coordinate, Ok(Coordinate::Local(0.0)));
+        test_parse!(tokens, Coordinate, Ok(Coordinate::Local(0.0)));
+        test_parse!(tokens, Coordinate, Ok(Coordinate::Local(5.0)));
+        let coordinate: Coordinate = Coordinate::parse(&mut tokens).unwrap();
+        assert_eq!(coordinate, Coordinate::Absolute(-3.0));
+        assert_eq!(coordinate.to_string(), "-3");
+        test_parse!(tokens, Coordinate, Ok(Coordinate::Absolute(1.5)));
+        test_parse!(tokens, Coordinate, Ok(Coordinate::Local(0.5)));
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("~1.2.3 true");
+        test_parse!(
+            tokens,
+            Coordinate,
+            Err(ParseError::invalid("~1.2.3", "Invalid coordinate offset"))
+        );
+        test_parse!(
+            tokens,
+            Coordinate,
+            Err(ParseError::invalid("true", "Expected coordinate"))
+        );
+    }
+
+    #[test]
+    fn test_tick_duration() {
+        let mut tokens: Vec<String> = tokenize!("20t 5s 0.5d 7 1.5s 2m");
+
+        let duration: TickDuration = TickDuration::parse(&mut tokens).unwrap();
+        assert_eq!(duration, TickDuration(20));
+        assert_eq!(duration.to_string(), "20t");
+        test_parse!(tokens, TickDuration, Ok(TickDuration(100)));
+        test_parse!(tokens, TickDuration, Ok(TickDuration(12000)));
+        test_parse!(tokens, TickDuration, Ok(TickDuration(7)));
+        test_parse!(tokens, TickDuration, Ok(TickDuration(30)));
+        test_parse!(tokens, TickDuration, Ok(TickDuration(2400)));
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("3h 18446744073709551615 0.01s 1.5t t inf");
+        test_parse!(
+            tokens,
+            TickDuration,
+            Err(ParseError::invalid(
+                "3h",
+                "Unknown time unit `h`, expected t, s, m, or d"
+            ))
+        );
+        test_parse!(
+            tokens,
+            TickDuration,
+            Err(ParseError::invalid(
+                "18446744073709551615",
+                "Duration out of range"
+            ))
+        );
+        for token in ["0.01s", "1.5t"] {
+            test_parse!(
+                tokens,
+                TickDuration,
+                Err(ParseError::invalid(
+                    token,
+                    "Durations must be a whole number of ticks"
+                ))
+            );
+        }
+        for token in ["t", "inf"] {
+            test_parse!(
+                tokens,
+                TickDuration,
+                Err(ParseError::invalid(token, "Invalid duration"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_resource_location() {
+        let mut tokens: Vec<String> =
+            tokenize!("minecraft:stone stone my_pack:block/ore_1 Stone a:b:c");
+        for expected in ["minecraft:stone", "minecraft:stone", "my_pack:block/ore_1"] {
+            let location: ResourceLocation = ResourceLocation::parse(&mut tokens).unwrap();
+            assert_eq!(location.to_string(), expected);
+        }
+        test_parse!(
+            tokens,
+            ResourceLocation,
+            Err(ParseError::invalid(
+                "Stone",
+                "Resource paths can only contain lowercase letters, digits, underscores, and slashes"
+            ))
+        );
+        test_parse!(
+            tokens,
+            ResourceLocation,
+            Err(ParseError::invalid(
+                "a:b:c",
+                "Resource paths can only contain lowercase letters, digits, underscores, and slashes"
+            ))
+        );
+    }
+}
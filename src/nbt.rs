@@ -0,0 +1,491 @@
+//! Module for parsing NBT in its text format, like `{Count:1b,id:"minecraft:stone"}`.
+
+use std::{fmt::Display, mem::discriminant};
+
+use crate::{
+    ensure, ensure_tokens,
+    parse::{parse_colon, peek_n, unescape_string, Literal, Parse, ParseError},
+};
+
+/// An NBT value.
+///
+/// Numbers take their type from their suffix: `b` for bytes, `s` for shorts, `l` for longs,
+/// `f` for floats, and `d` for doubles. Suffixes are case-insensitive.
+/// Numbers without a suffix are ints, or doubles if they contain a `.`.
+/// `true` and `false` are bytes, and other unquoted words are strings.
+///
+/// Compounds keep the order their entries were written in, and duplicate keys are an error.
+/// List elements must all have the same type.
+/// Typed arrays, like `[I; 1, 2, 3]`, are [`TypedArray`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nbt {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    List(Vec<Nbt>),
+    Array(TypedArray),
+    Compound(Vec<(String, Nbt)>),
+}
+impl Nbt {
+    /// Get the value of a compound entry.
+    /// Returns `None` if the key is missing or this is not a compound.
+    pub fn get(&self, key: &str) -> Option<&Nbt> {
+        match self {
+            Nbt::Compound(entries) => entries
+                .iter()
+                .find(|(other, _)| other == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+impl Display for Nbt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Nbt::*;
+        match self {
+            Byte(value) => write!(f, "{value}b"),
+            Short(value) => write!(f, "{value}s"),
+            Int(value) => write!(f, "{value}"),
+            Long(value) => write!(f, "{value}L"),
+            Float(value) => write!(f, "{value}f"),
+            Double(value) => write!(f, "{value}d"),
+            String(value) => write!(f, "{}", Literal::String(value.clone())),
+            List(values) => {
+                let values: Vec<std::string::String> = values.iter().map(Nbt::to_string).collect();
+                write!(f, "[{}]", values.join(","))
+            }
+            Array(array) => write!(f, "{array}"),
+            Compound(entries) => {
+                let entries: Vec<std::string::String> = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        if is_unquoted(key) {
+                            format!("{key}:{value}")
+                        } else {
+                            format!("{}:{value}", Literal::String(key.clone()))
+                        }
+                    })
+                    .collect();
+                write!(f, "{{{}}}", entries.join(","))
+            }
+        }
+    }
+}
+impl Parse for Nbt {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("value"))?;
+        match token.as_str() {
+            "{" => parse_compound(tokens),
+            "[" if peek_n(tokens, 1).is_some_and(|token| token == ";") => {
+                tokens.push(token);
+                TypedArray::parse(tokens).map(Nbt::Array)
+            }
+            "[" => parse_list(tokens),
+            "true" => Ok(Nbt::Byte(1)),
+            "false" => Ok(Nbt::Byte(0)),
+            "-" | "+" => {
+                let number: String = tokens
+                    .pop()
+                    .ok_or_else(|| ParseError::end_of_file("number"))?;
+                match parse_number(&format!("{token}{number}")) {
+                    Some(result) => result.map_err(|error| ParseError::invalid(number, error)),
+                    None => Err(ParseError::invalid(
+                        number,
+                        format!("Expected number after `{token}`"),
+                    )),
+                }
+            }
+            _ if token.starts_with('"') => unescape_string(&token)
+                .map(Nbt::String)
+                .map_err(|error| ParseError::invalid(token, error)),
+            _ => match parse_number(&token) {
+                Some(result) => result.map_err(|error| ParseError::invalid(token, error)),
+                None if is_unquoted(&token) => Ok(Nbt::String(token)),
+                None => Err(ParseError::invalid(token, "Invalid NBT value")),
+            },
+        }
+    }
+}
+
+/// A typed array, like `[B; 1b, 2b]`, `[I; 1, 2, 3]`, or `[L; 1L, 2L]`.
+/// Every element must have the type the array declares, so `[I; 1b]` is an error.
+/// Like lists, arrays may be empty, and may have a trailing comma.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypedArray {
+    Byte(Vec<i8>),
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+}
+impl Display for TypedArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (kind, values): (&str, Vec<String>) = match self {
+            TypedArray::Byte(values) => (
+                "B",
+                values.iter().map(|value| format!("{value}b")).collect(),
+            ),
+            TypedArray::Int(values) => ("I", values.iter().map(i32::to_string).collect()),
+            TypedArray::Long(values) => (
+                "L",
+                values.iter().map(|value| format!("{value}L")).collect(),
+            ),
+        };
+        write!(f, "[{kind};{}]", values.join(","))
+    }
+}
+impl Parse for TypedArray {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        ensure_tokens!(tokens, "[");
+        let kind: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("array type"))?;
+        let (mut array, name): (TypedArray, &str) = match kind.as_str() {
+            "B" => (TypedArray::Byte(vec![]), "bytes"),
+            "I" => (TypedArray::Int(vec![]), "ints"),
+            "L" => (TypedArray::Long(vec![]), "longs"),
+            _ => return Err(ParseError::invalid(kind, "Expected array type B, I, or L")),
+        };
+        ensure_tokens!(tokens, ";");
+
+        while tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+            // The token the element was written as, after any sign, like `true` rather than `1b`.
+            let token: String = match tokens.last().map(String::as_str) {
+                Some("-" | "+") => tokens.iter().rev().nth(1),
+                _ => tokens.last(),
+            }
+            .cloned()
+            .unwrap_or_default();
+            let value: Nbt = Nbt::parse(tokens)?;
+            match (&mut array, value) {
+                (TypedArray::Byte(values), Nbt::Byte(value)) => values.push(value),
+                (TypedArray::Int(values), Nbt::Int(value)) => values.push(value),
+                (TypedArray::Long(values), Nbt::Long(value)) => values.push(value),
+                _ => {
+                    return Err(ParseError::invalid(
+                        token,
+                        format!("Elements of a [{kind}; ...] array must be {name}"),
+                    ))
+                }
+            }
+
+            if tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+                ensure_tokens!(tokens, ",");
+            }
+        }
+        ensure_tokens!(tokens, "]");
+
+        Ok(array)
+    }
+}
+
+/// Whether a word can be written without quotes.
+fn is_unquoted(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a number with an optional type suffix, like `-5b` or `1.5f`.
+/// Returns `None` if the word doesn't look like a number, so it can be parsed as a string instead.
+fn parse_number(word: &str) -> Option<Result<Nbt, String>> {
+    let (body, suffix) = match word.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&word[..i], Some(c.to_ascii_lowercase())),
+        _ => (word, None),
+    };
+    let digits: &str = body.strip_prefix(['-', '+']).unwrap_or(body);
+    let is_integer: bool = !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+    let is_decimal: bool = digits.contains(|c: char| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.matches('.').count() == 1;
+
+    let out_of_range = |name: &str| format!("Number out of range for {name}");
+    Some(match (suffix, is_integer, is_decimal) {
+        (Some('b'), true, _) => body
+            .parse()
+            .map(Nbt::Byte)
+            .map_err(|_| out_of_range("byte")),
+        (Some('s'), true, _) => body
+            .parse()
+            .map(Nbt::Short)
+            .map_err(|_| out_of_range("short")),
+        (Some('l'), true, _) => body
+            .parse()
+            .map(Nbt::Long)
+            .map_err(|_| out_of_range("long")),
+        (None, true, _) => body.parse().map(Nbt::Int).map_err(|_| out_of_range("int")),
+        (Some('f'), true, _) | (Some('f'), _, true) => Ok(Nbt::Float(body.parse().ok()?)),
+        (Some('d'), true, _) | (Some('d'), _, true) | (None, _, true) => {
+            Ok(Nbt::Double(body.parse().ok()?))
+        }
+        _ => return None,
+    })
+}
+
+/// Parse the key of a compound entry and the `:` after it.
+/// Since `:` is not a special character, the key and value may be in one token, like `Count:1b`.
+fn parse_key(tokens: &mut Vec<String>) -> Result<String, ParseError> {
+    let token: String = tokens.pop().ok_or_else(|| ParseError::end_of_file("key"))?;
+    let key: String = if token.starts_with('"') {
+        unescape_string(&token).map_err(|error| ParseError::invalid(token, error))?
+    } else {
+        let (key, rest) = token.split_once(':').unwrap_or((&token, ""));
+        ensure!(
+            is_unquoted(key),
+            ParseError::invalid(key, "Invalid compound key")
+        );
+        if token.contains(':') {
+            tokens.push(format!(":{rest}"));
+        }
+        key.to_string()
+    };
+
+    parse_colon(tokens)?;
+    Ok(key)
+}
+
+/// Parse the entries of a compound, after the `{`.
+fn parse_compound(tokens: &mut Vec<String>) -> Result<Nbt, ParseError> {
+    let mut entries: Vec<(String, Nbt)> = vec![];
+    while tokens.last().ok_or_else(|| ParseError::end_of_file("}"))? != "}" {
+        let key: String = parse_key(tokens)?;
+        ensure!(
+            entries.iter().all(|(other, _)| *other != key),
+            ParseError::invalid(key.clone(), format!("Duplicate key `{key}`"))
+        );
+        entries.push((key, Nbt::parse(tokens)?));
+
+        if tokens.last().ok_or_else(|| ParseError::end_of_file("}"))? != "}" {
+            ensure_tokens!(tokens, ",");
+        }
+    }
+    ensure_tokens!(tokens, "}");
+
+    Ok(Nbt::Compound(entries))
+}
+
+/// Parse the elements of a list, after the `[`.
+fn parse_list(tokens: &mut Vec<String>) -> Result<Nbt, ParseError> {
+    let mut values: Vec<Nbt> = vec![];
+    while tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+        let value: Nbt = Nbt::parse(tokens)?;
+        ensure!(
+            values
+                .first()
+                .is_none_or(|first| discriminant(first) == discriminant(&value)),
+            ParseError::invalid(
+                value.to_string(),
+                "List elements must all have the same type"
+            )
+        );
+        values.push(value);
+
+        if tokens.last().ok_or_else(|| ParseError::end_of_file("]"))? != "]" {
+            ensure_tokens!(tokens, ",");
+        }
+    }
+    ensure_tokens!(tokens, "]");
+
+    Ok(Nbt::List(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_parse, test_parse_exact, tokenize};
+
+    use super::*;
+
+    #[test]
+    fn test_nbt() {
+        let mut tokens: Vec<String> =
+            tokenize!("{Count:1b, id:\"minecraft:stone\", tag: {Damage: -5s, Tags: [a, b]}}");
+
+        let nbt: Nbt = Nbt::parse(&mut tokens).unwrap();
+        assert_eq!(nbt.get("Count"), Some(&Nbt::Byte(1)));
+        assert_eq!(
+            nbt.get("id"),
+            Some(&Nbt::String("minecraft:stone".to_string()))
+        );
+        assert_eq!(
+            nbt.get("tag").and_then(|tag| tag.get("Damage")),
+            Some(&Nbt::Short(-5))
+        );
+        assert_eq!(nbt.get("missing"), None);
+        assert_eq!(
+            nbt.to_string(),
+            "{Count:1b,id:\"minecraft:stone\",tag:{Damage:-5s,Tags:[\"a\",\"b\"]}}"
+        );
+        assert!(tokens.is_empty());
+
+        test_parse_exact!("{}", Nbt, Ok(Nbt::Compound(vec![])));
+        test_parse_exact!("[]", Nbt, Ok(Nbt::List(vec![])));
+        test_parse_exact!(
+            "{\"a key\" : 1, b:true,}",
+            Nbt,
+            Ok(Nbt::Compound(vec![
+                ("a key".to_string(), Nbt::Int(1)),
+                ("b".to_string(), Nbt::Byte(1))
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_nbt_numbers() {
+        let mut tokens: Vec<String> = tokenize!("1b 2S 3 -4L 4.0f .5F 5.0d 6.5 +7D 1e5 -1.5f");
+
+        test_parse!(tokens, Nbt, Ok(Nbt::Byte(1)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Short(2)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Int(3)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Long(-4)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Float(4.0)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Float(0.5)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Double(5.0)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Double(6.5)));
+        test_parse!(tokens, Nbt, Ok(Nbt::Double(7.0)));
+        test_parse!(tokens, Nbt, Ok(Nbt::String("1e5".to_string())));
+        let nbt: Nbt = Nbt::parse(&mut tokens).unwrap();
+        assert_eq!(nbt, Nbt::Float(-1.5));
+        assert_eq!(nbt.to_string(), "-1.5f");
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("128b 99999999999 -x");
+        test_parse!(
+            tokens,
+            Nbt,
+            Err(ParseError::invalid("128b", "Number out of range for byte"))
+        );
+        test_parse!(
+            tokens,
+            Nbt,
+            Err(ParseError::invalid(
+                "99999999999",
+                "Number out of range for int"
+            ))
+        );
+        test_parse!(
+            tokens,
+            Nbt,
+            Err(ParseError::invalid("x", "Expected number after `-`"))
+        );
+    }
+
+    #[test]
+    fn test_nbt_errors() {
+        let mut tokens: Vec<String> = tokenize!("[1, 2b]");
+        test_parse!(
+            tokens,
+            Nbt,
+            Err(ParseError::invalid(
+                "2b",
+                "List elements must all have the same type"
+            ))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("{a:1,a:2}");
+        test_parse!(
+            tokens,
+            Nbt,
+            Err(ParseError::invalid("a", "Duplicate key `a`"))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("{a 1}");
+        test_parse!(tokens, Nbt, Err(ParseError::expected("1", ":")));
+
+        let mut tokens: Vec<String> = tokenize!("{a:1 b:2}");
+        test_parse!(tokens, Nbt, Err(ParseError::expected("b:2", ",")));
+
+        let mut tokens: Vec<String> = tokenize!("{a:1");
+        test_parse!(tokens, Nbt, Err(ParseError::end_of_file("}")));
+
+        let mut tokens: Vec<String> = tokenize!(r#""\q" {"\q":1}"#);
+        test_parse!(
+            tokens,
+            Nbt,
+            Err(ParseError::invalid(
+                r#""\q""#,
+                "Unknown escape `\\q` at index 1"
+            ))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Nbt,
+            Err(ParseError::invalid(
+                r#""\q""#,
+                "Unknown escape `\\q` at index 1"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_typed_array() {
+        test_parse_exact!(
+            "[B; 1b, -2b, true]",
+            TypedArray,
+            Ok(TypedArray::Byte(vec![1, -2, 1]))
+        );
+        test_parse_exact!("[I;]", TypedArray, Ok(TypedArray::Int(vec![])));
+        test_parse_exact!(
+            "[L; 1L, 20000000000l,]",
+            TypedArray,
+            Ok(TypedArray::Long(vec![1, 20000000000]))
+        );
+
+        let nbt: Nbt = Nbt::parse_str("{a: [I; 1, 2, 3], b: [1, 2]}").unwrap();
+        assert_eq!(
+            nbt.get("a"),
+            Some(&Nbt::Array(TypedArray::Int(vec![1, 2, 3])))
+        );
+        assert_eq!(nbt.to_string(), "{a:[I;1,2,3],b:[1,2]}");
+        assert_eq!(Nbt::parse_str(&nbt.to_string()), Ok(nbt));
+        assert_eq!(TypedArray::Long(vec![-1]).to_string(), "[L;-1L]");
+
+        let mut tokens: Vec<String> =
+            tokenize!("[I; true] [I; -2b] [I; 1, 2b] [B; 1] [S; 1] [I 1]");
+        test_parse!(
+            tokens,
+            TypedArray,
+            Err(ParseError::invalid(
+                "true",
+                "Elements of a [I; ...] array must be ints"
+            ))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            TypedArray,
+            Err(ParseError::invalid(
+                "2b",
+                "Elements of a [I; ...] array must be ints"
+            ))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            TypedArray,
+            Err(ParseError::invalid(
+                "2b",
+                "Elements of a [I; ...] array must be ints"
+            ))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            TypedArray,
+            Err(ParseError::invalid(
+                "1",
+                "Elements of a [B; ...] array must be bytes"
+            ))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            TypedArray,
+            Err(ParseError::invalid("S", "Expected array type B, I, or L"))
+        );
+        tokens.truncate(tokens.len() - 3);
+        test_parse!(tokens, TypedArray, Err(ParseError::expected("1", ";")));
+    }
+}
@@ -1,10 +1,25 @@
 //! Module for parsing tokens.
 
-use std::fmt::Display;
+use std::{
+    cell::Cell,
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+        NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+    },
+    ops::Deref,
+    str::FromStr,
+    time::Duration,
+};
 
 use thiserror::Error;
 
-use crate::ensure;
+use crate::{
+    ensure,
+    intern::{Interner, Symbol},
+    tokenize::{Span, Token, TokenKind, Tokenizer},
+};
 
 /// Ensure that the next tokens in the list match the given tokens.
 /// This will remove the tokens from the list.
@@ -14,24 +29,489 @@ macro_rules! ensure_tokens {
     ($tokens:ident, $($token:expr),+) => {
         $(
             $crate::ensure!(
-                $tokens.last().ok_or($crate::parse::ParseError::EndOfFile)? == $token,
-                $crate::parse::ParseError::InvalidToken {
-                    token: $tokens.last().unwrap().clone(),
-                    error: format!("Expected {}", $token),
-                }
+                $tokens
+                    .last()
+                    .ok_or_else(|| $crate::parse::ParseError::end_of_file(&$token.to_string()))?
+                    == $token,
+                $crate::parse::ParseError::expected(
+                    $tokens.last().unwrap(),
+                    &$token.to_string(),
+                )
             );
             $tokens.pop();
         )+
     };
 }
 
+/// Parse a sequence of expected tokens and typed values, like
+/// `parse_sequence!(tokens, "let", name: Identifier, "=", value: Literal)`.
+/// String literals are expected tokens, checked with [`ensure_tokens!`], and `name: Type` items
+/// are parsed in order with `Type::parse`.
+///
+/// This evaluates to a `Result` of a tuple of the parsed values, in order, so a single value is
+/// a one-element tuple. The tokens are consumed as far as parsing got, even on failure.
+///
+/// ```
+/// use specmc_base::{parse::{Identifier, Literal, ParseError}, parse_sequence, tokenize};
+///
+/// let mut tokens: Vec<String> = tokenize!("let x = 5");
+/// let (name, value) = parse_sequence!(tokens, "let", name: Identifier, "=", value: Literal)?;
+/// assert_eq!(name, Identifier("x".to_string()));
+/// assert_eq!(value, Literal::Integer(5));
+/// # Ok::<(), ParseError>(())
+/// ```
+#[macro_export]
+macro_rules! parse_sequence {
+    (@munch $tokens:ident [$($value:ident)*] $token:literal $(, $($rest:tt)*)?) => {
+        $crate::ensure_tokens!($tokens, $token);
+        $crate::parse_sequence!(@munch $tokens [$($value)*] $($($rest)*)?)
+    };
+    (@munch $tokens:ident [$($value:ident)*] $name:ident : $ty:ty $(, $($rest:tt)*)?) => {
+        let $name: $ty = <$ty as $crate::parse::Parse>::parse($tokens)?;
+        $crate::parse_sequence!(@munch $tokens [$($value)* $name] $($($rest)*)?)
+    };
+    (@munch $tokens:ident [$($value:ident)*]) => {
+        return Ok(($($value,)*))
+    };
+    ($tokens:ident, $($item:tt)+) => {
+        (|| -> Result<_, $crate::parse::ParseError> {
+            let $tokens: &mut Vec<String> = $tokens.as_mut();
+            $crate::parse_sequence!(@munch $tokens [] $($item)+);
+        })()
+    };
+}
+
+/// Implement `Parse` and `Display` for a struct from a single grammar description.
+/// The description is a sequence of string literals, which are expected tokens,
+/// and field names, which are parsed with the `Parse` impl of the field's type.
+/// `Display` writes the same sequence separated by spaces, so its output parses back into the same value.
+///
+/// ```
+/// use specmc_base::{impl_grammar, parse::{Identifier, Literal}};
+///
+/// struct Assignment {
+///     name: Identifier,
+///     value: Literal,
+/// }
+/// impl_grammar!(Assignment { "let" name "=" value ";" });
+/// ```
+#[macro_export]
+macro_rules! impl_grammar {
+    (@parse $tokens:ident [$($field:ident)*] $token:literal $($rest:tt)*) => {
+        $crate::ensure_tokens!($tokens, $token);
+        $crate::impl_grammar!(@parse $tokens [$($field)*] $($rest)*)
+    };
+    (@parse $tokens:ident [$($field:ident)*] $next:ident $($rest:tt)*) => {
+        let $next = $crate::parse::Parse::parse($tokens)?;
+        $crate::impl_grammar!(@parse $tokens [$($field)* $next] $($rest)*)
+    };
+    (@parse $tokens:ident [$($field:ident)*]) => {
+        return Ok(Self { $($field),* })
+    };
+    (@display $self:ident $token:literal) => {
+        $token.to_string()
+    };
+    (@display $self:ident $field:ident) => {
+        $self.$field.to_string()
+    };
+    ($ty:ident { $($item:tt)* }) => {
+        impl $crate::parse::Parse for $ty {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, $crate::parse::ParseError> {
+                $crate::impl_grammar!(@parse tokens [] $($item)*);
+            }
+        }
+        impl std::fmt::Display for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let items: Vec<String> = vec![$($crate::impl_grammar!(@display self $item)),*];
+                write!(f, "{}", items.join(" "))
+            }
+        }
+    };
+}
+
+/// Declare a struct, and implement `Parse` and `Display` for it, from a single grammar production.
+/// Like [`impl_grammar!`], string literals are expected tokens, but fields are written as `name:Type`.
+/// Fields are public, and the struct derives `Debug`, `Clone`, and `PartialEq`.
+/// Types must be a single token, so wrap generic types in parentheses, like `flags:(Flags<Identifier>)`.
+///
+/// ```
+/// use specmc_base::{grammar, parse::Parse, tokenize};
+///
+/// grammar!(pub Point = "(" x:i64 "," y:i64 ")");
+///
+/// let mut tokens: Vec<String> = tokenize!("(1, -2)");
+/// assert_eq!(Point::parse(&mut tokens), Ok(Point { x: 1, y: -2 }));
+/// ```
+#[macro_export]
+macro_rules! grammar {
+    (@munch [$($meta:tt)*] $vis:vis $name:ident [$($field:ident $ty:tt)*] [$($item:tt)*]
+        $token:literal $($rest:tt)*) => {
+        $crate::grammar!(@munch [$($meta)*] $vis $name [$($field $ty)*] [$($item)* $token] $($rest)*);
+    };
+    (@munch [$($meta:tt)*] $vis:vis $name:ident [$($field:ident $ty:tt)*] [$($item:tt)*]
+        $next:ident : $next_ty:tt $($rest:tt)*) => {
+        $crate::grammar!(@munch [$($meta)*] $vis $name [$($field $ty)* $next $next_ty] [$($item)* $next] $($rest)*);
+    };
+    (@munch [$($meta:tt)*] $vis:vis $name:ident [$($field:ident $ty:tt)*] [$($item:tt)*]) => {
+        $($meta)*
+        #[derive(Debug, Clone, PartialEq)]
+        // Parentheses around generic types are needed to pass them as a single token.
+        #[allow(unused_parens)]
+        $vis struct $name {
+            $(pub $field: $ty,)*
+        }
+        $crate::impl_grammar!($name { $($item)* });
+    };
+    ($(#[$meta:meta])* $vis:vis $name:ident = $($rest:tt)+) => {
+        $crate::grammar!(@munch [$(#[$meta])*] $vis $name [] [] $($rest)+);
+    };
+}
+
+/// Declare a unit struct that parses by consuming a single keyword.
+/// The struct displays as the keyword.
+///
+/// ```
+/// use specmc_base::keyword_type;
+///
+/// keyword_type!(pub Struct, "struct");
+/// ```
+#[macro_export]
+macro_rules! keyword_type {
+    ($(#[$meta:meta])* $vis:vis $name:ident, $keyword:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        $vis struct $name;
+        impl $crate::parse::Parse for $name {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, $crate::parse::ParseError> {
+                $crate::ensure_tokens!(tokens, $keyword);
+                Ok($name)
+            }
+        }
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", $keyword)
+            }
+        }
+    };
+}
+
+/// Declare an enum whose variants are a fixed set of strings, like `north` or `"north"`.
+/// The token can be written bare or as a string literal, and must match one of the strings exactly.
+/// Otherwise, [`ParseError::ExpectedOneOf`] is returned, and the token isn't consumed.
+/// The enum displays as its bare string, `as_str` gives it as a `&str`, and `ALL` lists every variant.
+///
+/// ```
+/// use specmc_base::{parse::Parse, string_enum, tokenize};
+///
+/// string_enum!(pub Direction { North = "north", South = "south" });
+///
+/// let mut tokens: Vec<String> = tokenize!("north \"south\"");
+/// assert_eq!(Direction::parse(&mut tokens), Ok(Direction::North));
+/// assert_eq!(Direction::parse(&mut tokens), Ok(Direction::South));
+/// assert_eq!(Direction::South.to_string(), "south");
+/// ```
+#[macro_export]
+macro_rules! string_enum {
+    ($(#[$meta:meta])* $vis:vis $name:ident { $($variant:ident = $string:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis enum $name {
+            $($variant),+
+        }
+        impl $name {
+            /// Every variant, in declaration order.
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            /// The string of the variant.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $string),+
+                }
+            }
+        }
+        impl $crate::parse::Parse for $name {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, $crate::parse::ParseError> {
+                let token: &String = tokens.last().ok_or_else(|| {
+                    $crate::parse::ParseError::end_of_file(&format!("one of {}", [$($string),+].join(", ")))
+                })?;
+                let value: String = if token.starts_with('"') {
+                    match $crate::parse::Literal::parse(&mut vec![token.clone()]) {
+                        Ok($crate::parse::Literal::String(value)) => value,
+                        _ => token.clone(),
+                    }
+                } else {
+                    token.clone()
+                };
+                let variant: $name = match value.as_str() {
+                    $($string => $name::$variant,)+
+                    _ => {
+                        return Err($crate::parse::ParseError::ExpectedOneOf {
+                            token: token.clone(),
+                            expected: vec![$($string.to_string()),+],
+                        })
+                    }
+                };
+                tokens.pop();
+                Ok(variant)
+            }
+        }
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+    };
+}
+
+/// An error that occurred while parsing.
+///
+/// Equality compares everything, including spans.
+/// Use [`ParseError::eq_ignoring_span`] to compare errors regardless of where they occurred.
 #[derive(Debug, Error, Clone, PartialEq, Eq)]
 pub enum ParseError {
-    #[error("Unexpected EOF")]
-    EndOfFile,
+    /// The tokens ran out. If it is known what was expected instead, it is in `expected`.
+    #[error("Unexpected EOF{}", .expected.as_ref().map_or(String::new(), |expected| format!(", expected {expected}")))]
+    EndOfFile { expected: Option<String> },
 
+    /// `error` is the message shown to users. If it is known what was expected instead,
+    /// like `=` or `, or ]`, it is also in `expected`, for tools like quick-fixes.
+    /// The message already says what was expected, so `expected` isn't displayed separately.
     #[error("Invalid token: {error}: {token}")]
-    InvalidToken { token: String, error: String },
+    InvalidToken {
+        token: String,
+        error: String,
+        expected: Option<String>,
+    },
+
+    #[error("Expected one of {}: {token}", .expected.join(", "))]
+    ExpectedOneOf {
+        token: String,
+        expected: Vec<String>,
+    },
+
+    #[error("Recursion limit exceeded")]
+    RecursionLimit,
+
+    #[error("{} at line {}, column {}", .error, .span.line, .span.column)]
+    Spanned { span: Span, error: Box<ParseError> },
+
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        source: Box<ParseError>,
+    },
+}
+
+/// Pairs of tokens that are easily mistaken for each other.
+const NEAR_MISSES: &[(&str, &str)] = &[("=", "=="), ("==", "="), (",", ";"), (";", ",")];
+
+impl ParseError {
+    /// Where the error occurred, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::Spanned { span, .. } => Some(*span),
+            ParseError::Context { source, .. } => source.span(),
+            _ => None,
+        }
+    }
+
+    /// Attach a span to the error, replacing any existing span.
+    pub fn with_span(self, span: Span) -> Self {
+        let error: ParseError = match self {
+            ParseError::Spanned { error, .. } => *error,
+            error => error,
+        };
+        ParseError::Spanned {
+            span,
+            error: Box::new(error),
+        }
+    }
+
+    /// The error without its span.
+    pub fn unspanned(&self) -> &ParseError {
+        match self {
+            ParseError::Spanned { error, .. } => error.unspanned(),
+            error => error,
+        }
+    }
+
+    /// The error with all spans removed, including those inside contexts.
+    pub fn without_spans(&self) -> ParseError {
+        match self.unspanned() {
+            ParseError::Context { context, source } => ParseError::Context {
+                context: context.clone(),
+                source: Box::new(source.without_spans()),
+            },
+            error => error.clone(),
+        }
+    }
+
+    /// Compare two errors, ignoring their spans, including those inside contexts.
+    pub fn eq_ignoring_span(&self, other: &ParseError) -> bool {
+        match (self.unspanned(), other.unspanned()) {
+            (
+                ParseError::Context { context, source },
+                ParseError::Context {
+                    context: other_context,
+                    source: other_source,
+                },
+            ) => context == other_context && source.eq_ignoring_span(other_source),
+            (error, other) => error == other,
+        }
+    }
+
+    /// Render the error as a message with the line of `source` it occurred on, and a caret under
+    /// the offending token, for printing in command line tools. `source` must be the text that was
+    /// parsed, like the input to [`Parse::parse_str`].
+    ///
+    /// If the error has no span, or the span doesn't fit in the source, this is just the message.
+    ///
+    /// ```
+    /// use specmc_base::parse::{Identifier, KeyValue, Literal, Parse};
+    ///
+    /// let source: &str = "a 2";
+    /// let error = KeyValue::<Identifier, Literal>::parse_str(source).unwrap_err();
+    /// assert_eq!(
+    ///     error.render(source),
+    ///     "error: Invalid token: Expected =: 2\n --> line 1, column 3\n  |\n1 | a 2\n  |   ^"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let message: String = self.without_spans().to_string();
+        let Some(span) = self.span() else {
+            return format!("error: {message}");
+        };
+        let Some(before) = source.get(..span.start) else {
+            return format!("error: {message}");
+        };
+
+        let line_start: usize = before.rfind(['\n', '\r']).map_or(0, |i| i + 1);
+        let line_end: usize = source[span.start..]
+            .find(['\n', '\r'])
+            .map_or(source.len(), |i| span.start + i);
+        let line: &str = &source[line_start..line_end];
+        // Keep tabs in the indentation, so the caret lines up however tabs are displayed.
+        let indent: String = source[line_start..span.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width: usize = source
+            .get(span.start..span.end.min(line_end))
+            .map_or(0, |token| token.chars().count())
+            .max(1);
+
+        let number: String = span.line.to_string();
+        let gutter: String = " ".repeat(number.len());
+        format!(
+            "error: {message}\n{gutter}--> line {}, column {}\n{gutter} |\n{number} | {line}\n{gutter} | {indent}{}",
+            span.line,
+            span.column,
+            "^".repeat(width),
+        )
+    }
+
+    /// Wrap the error with what was being parsed when it occurred, like `"In block body"`.
+    /// Wrapping repeatedly builds a chain, which is displayed from the outermost context in.
+    pub fn with_context(self, context: impl Into<String>) -> Self {
+        ParseError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Create a [`ParseError::EndOfFile`] error, for when the tokens ran out
+    /// but `expected` was expected.
+    pub fn end_of_file(expected: &str) -> Self {
+        ParseError::EndOfFile {
+            expected: Some(expected.to_string()),
+        }
+    }
+
+    /// Create a [`ParseError::EndOfFile`] error that doesn't say what was expected.
+    pub fn eof() -> Self {
+        ParseError::EndOfFile { expected: None }
+    }
+
+    /// Whether the error is from running out of tokens, whether or not it says what was expected.
+    pub fn is_end_of_file(&self) -> bool {
+        match self.unspanned() {
+            ParseError::EndOfFile { .. } => true,
+            ParseError::Context { source, .. } => source.is_end_of_file(),
+            _ => false,
+        }
+    }
+
+    /// Create a [`ParseError::InvalidToken`] error.
+    pub fn invalid(token: impl Into<String>, error: impl Into<String>) -> Self {
+        ParseError::InvalidToken {
+            token: token.into(),
+            error: error.into(),
+            expected: None,
+        }
+    }
+
+    /// Create a [`ParseError::InvalidToken`] error for when `expected` was expected,
+    /// but `found` was found instead.
+    pub fn expected(found: impl Into<String>, expected: &str) -> Self {
+        let found: String = found.into();
+        let error: String = ParseError::expected_message(&found, expected);
+        ParseError::InvalidToken {
+            token: found,
+            error,
+            expected: Some(expected.to_string()),
+        }
+    }
+
+    /// The token the error is about, if there is one.
+    /// This looks through spans and contexts.
+    pub fn found_token(&self) -> Option<&str> {
+        match self.unspanned() {
+            ParseError::InvalidToken { token, .. } | ParseError::ExpectedOneOf { token, .. } => {
+                Some(token)
+            }
+            ParseError::Context { source, .. } => source.found_token(),
+            _ => None,
+        }
+    }
+
+    /// What was expected instead, like `=` or `, or ]`, if the error says.
+    /// This looks through spans and contexts.
+    pub fn expected_token(&self) -> Option<&str> {
+        match self.unspanned() {
+            ParseError::InvalidToken { expected, .. } => expected.as_deref(),
+            ParseError::EndOfFile { expected } => expected.as_deref(),
+            ParseError::Context { source, .. } => source.expected_token(),
+            _ => None,
+        }
+    }
+
+    /// Format the message for when `expected` was expected, but `found` was found instead.
+    /// If the two are easily mistaken for each other, a hint is added to the message.
+    pub fn expected_message(found: &str, expected: &str) -> String {
+        if NEAR_MISSES.contains(&(found, expected)) {
+            format!("Expected {expected} (found `{found}`, did you mean `{expected}`?)")
+        } else {
+            format!("Expected {expected}")
+        }
+    }
+}
+
+/// With the `miette` feature, an error with a span labels it, with what was expected if that is known.
+/// The error doesn't keep the source, so attach it with [`miette::Report::with_source_code`]
+/// to show the snippet.
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for ParseError {
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let span: Span = self.span()?;
+        let label: Option<String> = self
+            .expected_token()
+            .map(|expected| format!("expected {expected}"));
+        Some(Box::new(std::iter::once(miette::LabeledSpan::new(
+            label,
+            span.start,
+            span.end - span.start,
+        ))))
+    }
 }
 
 pub trait Parse
@@ -41,143 +521,3779 @@ where
     /// Parse a list of tokens into an object, consuming the tokens as needed.
     /// The token list is consumed in reverse order.
     /// If this fails, it is **not** guaranteed that no tokens have been consumed.
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
     fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError>;
+
+    /// Tokenize a string and parse it into an object.
+    /// Comments are skipped, and any tokens left over after parsing are ignored.
+    /// Errors have the span of the token where parsing failed attached.
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    fn parse_str(input: &str) -> Result<Self, ParseError> {
+        parse_str_with(input, |tokens, _| Self::parse(tokens))
+    }
 }
 
-/// An identifier.
-/// The identifier must not be empty or start with a number, and can only contain letters, numbers, and underscores.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Identifier(pub String);
-impl Display for Identifier {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+/// Tokenize a string and parse it like [`Parse::parse_str`], but with a parse function that is
+/// also given the source tokens, in order, for parsers that need the text between tokens.
+/// A token in the token list is `source[source.len() - 1 - i]`, for its index `i`.
+pub(crate) fn parse_str_with<T>(
+    input: &str,
+    parse: impl FnOnce(&mut Vec<String>, &[Token]) -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    let mut tokenizer: Tokenizer = Tokenizer::new(input);
+    let source: Vec<Token> = tokenizer
+        .by_ref()
+        .filter(|token| !token.kind().is_comment())
+        .collect();
+    let mut tokens: Vec<String> = source
+        .iter()
+        .rev()
+        .map(|token| token.text.clone())
+        .collect();
+
+    parse(&mut tokens, &source).map_err(|error| {
+        let span: Span =
+            locate_error(&source, tokens.len(), &error).unwrap_or_else(|| tokenizer.cursor_span());
+        error.with_span(span)
+    })
+}
+
+/// Find the span of the token an error occurred at, given how many tokens were left unparsed.
+/// Parsers may or may not consume the offending token before failing,
+/// so both the next and the last consumed token are checked against the error's token.
+fn locate_error(source: &[Token], remaining: usize, error: &ParseError) -> Option<Span> {
+    let next: usize = source.len().saturating_sub(remaining);
+    let token: Option<&String> = match error.unspanned() {
+        ParseError::EndOfFile { .. } => return None,
+        ParseError::InvalidToken { token, .. } | ParseError::ExpectedOneOf { token, .. } => {
+            Some(token)
+        }
+        _ => None,
+    };
+
+    let candidates: [Option<&Token>; 2] = [
+        source.get(next),
+        next.checked_sub(1).and_then(|i| source.get(i)),
+    ];
+    candidates
+        .iter()
+        .flatten()
+        .find(|candidate| token.is_some_and(|token| &candidate.text == token))
+        .or(candidates[0].as_ref())
+        .map(|token| token.span)
+}
+
+/// The default maximum nesting depth of [`parse_nested`].
+pub const DEFAULT_RECURSION_LIMIT: usize = 256;
+
+thread_local! {
+    static RECURSION_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static RECURSION_LIMIT: Cell<usize> = const { Cell::new(DEFAULT_RECURSION_LIMIT) };
+}
+
+/// Set the maximum nesting depth of [`parse_nested`] for the current thread.
+pub fn set_recursion_limit(limit: usize) {
+    RECURSION_LIMIT.set(limit);
+}
+
+/// Get the maximum nesting depth of [`parse_nested`] for the current thread.
+pub fn recursion_limit() -> usize {
+    RECURSION_LIMIT.get()
+}
+
+/// Run a nested parse, guarding against unbounded recursion.
+/// Recursive `Parse` impls should wrap their recursive calls in this.
+/// If the nesting depth exceeds the recursion limit, [`ParseError::RecursionLimit`] is returned
+/// instead of overflowing the stack.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_nested<T>(
+    tokens: &mut Vec<String>,
+    parse: impl FnOnce(&mut Vec<String>) -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    struct DepthGuard(usize);
+    impl Drop for DepthGuard {
+        fn drop(&mut self) {
+            RECURSION_DEPTH.set(self.0);
+        }
     }
+
+    let depth: usize = RECURSION_DEPTH.get();
+    ensure!(depth < recursion_limit(), ParseError::RecursionLimit);
+    RECURSION_DEPTH.set(depth + 1);
+    let _guard: DepthGuard = DepthGuard(depth);
+
+    parse(tokens)
 }
-impl Parse for Identifier {
-    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
-        let value: String = tokens.pop().ok_or(ParseError::EndOfFile)?;
 
-        ensure!(
-            !value.is_empty(),
-            ParseError::InvalidToken {
-                token: value,
-                error: "Empty identifier".to_string()
-            }
-        );
+/// Look at an upcoming token without consuming it.
+/// `n` counts from the next token to be parsed, so `peek_n(tokens, 0)` is the next token,
+/// `peek_n(tokens, 1)` is the one after it, and so on.
+/// Since token lists are stored in reverse, this is `tokens[tokens.len() - 1 - n]`.
+pub fn peek_n(tokens: &[String], n: usize) -> Option<&String> {
+    tokens.iter().rev().nth(n)
+}
 
-        let mut chars: std::str::Chars = value.chars();
-        ensure!(
-            chars
-                .next()
-                .map(|c| c.is_ascii_alphabetic() || c == '_')
-                .unwrap(),
-            ParseError::InvalidToken {
-                token: value,
-                error: "Identifiers must not start with a number and can only contain letters, numbers, and underscores".to_string()
-            }
-        );
-        ensure!(
-            chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
-            ParseError::InvalidToken {
-                token: value,
-                error: "Identifiers can only contain letters, numbers, and underscores".to_string()
-            }
-        );
+/// Parse `T`, restoring the tokens to exactly how they were unless the parse is kept.
+/// `keep` is given the result and whether any tokens were consumed, and decides whether
+/// the tokens are left after the parse.
+fn parse_restoring<T: Parse>(
+    tokens: &mut Vec<String>,
+    keep: impl Fn(&Result<T, ParseError>, bool) -> bool,
+) -> Result<T, ParseError> {
+    let snapshot: Vec<String> = tokens.clone();
+    let result: Result<T, ParseError> = T::parse(tokens);
+    if !keep(&result, tokens.len() < snapshot.len()) {
+        *tokens = snapshot;
+    }
+    result
+}
+
+/// The kind of the next token, without consuming it, so grammar code can branch on it
+/// before committing to a parse. Returns `None` if the tokens ran out.
+///
+/// Tokens are classified by [`TokenKind::of`]:
+/// - String literals, starting with `"`, are [`TokenKind::String`].
+/// - Numbers, like `5`, `0x1F`, `1e-5`, and `.5`, are [`TokenKind::Number`].
+///   A sign is a separate token, so `-5` starts with the `-` special token.
+/// - The default special tokens are all [`TokenKind::Special`]: delimiters like `(`, `]`, and `}`,
+///   separators like `,`, `;`, and `.`, operators like `=`, `==`, `+`, `**`, `|`, and `!`,
+///   and `#`, `@`, `%`, `~`, and `^`. Coordinates like `~1` and `^-2` are special too.
+/// - Everything else is a [`TokenKind::Word`], like identifiers, keywords, and `true`.
+///   This includes operators added with [`Tokenizer::operators`], like `->`.
+///
+/// Token lists for parsing have no comments, so comment kinds only come up if they were kept.
+pub fn peek_kind(tokens: &[String]) -> Option<TokenKind> {
+    tokens.last().map(|token| TokenKind::of(token))
+}
 
-        Ok(Identifier(value))
+/// Consume the next token if it is the given keyword.
+/// Returns whether the keyword was present. If it was not, the tokens are left untouched.
+pub fn parse_optional_keyword(tokens: &mut Vec<String>, keyword: &str) -> bool {
+    if tokens.last().is_some_and(|token| token == keyword) {
+        tokens.pop();
+        true
+    } else {
+        false
     }
 }
 
-/// A literal value.
-/// This can be a boolean, integer, float, or string.
+/// Parse a clause introduced by a keyword, like `else { ... }`, if the keyword is next.
+/// If it is, the keyword is consumed and the body is parsed, and errors in the body are returned.
+/// If it isn't, `None` is returned and the tokens are left untouched.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_if_keyword<T>(
+    tokens: &mut Vec<String>,
+    keyword: &str,
+    parse_body: impl FnOnce(&mut Vec<String>) -> Result<T, ParseError>,
+) -> Result<Option<T>, ParseError> {
+    if parse_optional_keyword(tokens, keyword) {
+        parse_body(tokens).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Parse a sequence of statements, each terminated by a `;`.
+/// Parsing stops when the tokens run out or a `}` is reached. The `}` is not consumed.
+/// Empty statements (a `;` on its own, like in `a;;b;`) are skipped.
+/// This is the terminated form of [`parse_until`], ending at [`ListEnd::Token`] or [`ListEnd::EndOfFile`].
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_statements<T: Parse>(tokens: &mut Vec<String>) -> Result<Vec<T>, ParseError> {
+    parse_list(
+        tokens,
+        &[ListEnd::Token("}"), ListEnd::EndOfFile],
+        ";",
+        Separators::After,
+    )
+}
+
+/// Parse items while the next token matches the given predicate, like `|token| token != "}"`.
+/// Parsing stops when the predicate fails or the tokens run out. The token that failed
+/// the predicate is not consumed.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_while<T: Parse>(
+    tokens: &mut Vec<String>,
+    predicate: impl Fn(&str) -> bool,
+) -> Result<Vec<T>, ParseError> {
+    let mut items: Vec<T> = vec![];
+    while tokens.last().is_some_and(|token| predicate(token)) {
+        items.push(T::parse(tokens)?);
+    }
+    Ok(items)
+}
+
+/// Lazily parse successive values until the tokens run out.
+/// Errors are yielded as items. After an error, the iterator stops, since the tokens may be
+/// left partway through a value.
+/// If the tokens are empty to begin with, nothing is yielded.
+#[must_use = "the iterator is lazy, so nothing is parsed unless it is used"]
+pub fn parse_iter<T: Parse>(
+    tokens: &mut Vec<String>,
+) -> impl Iterator<Item = Result<T, ParseError>> + '_ {
+    let mut failed: bool = false;
+    std::iter::from_fn(move || {
+        if failed || tokens.is_empty() {
+            return None;
+        }
+        let result: Result<T, ParseError> = T::parse(tokens);
+        failed = result.is_err();
+        Some(result)
+    })
+}
+
+/// Tokens that error recovery skips to.
+const SYNC_TOKENS: &[&str] = &[";", "}"];
+
+/// Discard tokens until the next one is one of the synchronization tokens, like `&[";", "}"]`,
+/// or the tokens run out. The synchronization token is not consumed.
+/// Returns how many tokens were skipped.
+///
+/// This is the low-level primitive behind panic-mode error recovery, like in [`Recovered`]:
+/// after an error, skip to a known point and continue parsing from there.
+pub fn skip_to_sync(tokens: &mut Vec<String>, sync: &[&str]) -> usize {
+    let mut skipped: usize = 0;
+    while tokens
+        .last()
+        .is_some_and(|token| !sync.contains(&token.as_str()))
+    {
+        tokens.pop();
+        skipped += 1;
+    }
+    skipped
+}
+
+/// A value that may have failed to parse.
+/// If parsing `T` fails, the error is stored instead of being propagated, and tokens
+/// are skipped up to (but not including) the next `;` or `}`, so parsing can continue.
+/// Parsing a `Recovered` always succeeds.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Literal {
-    Boolean(bool),
-    Integer(isize),
-    Float(f64),
-    String(String),
+pub enum Recovered<T> {
+    Parsed(T),
+    Error(ParseError),
 }
-impl Display for Literal {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use Literal::*;
+impl<T> Recovered<T> {
+    /// Convert back into a regular parse result.
+    pub fn into_result(self) -> Result<T, ParseError> {
         match self {
-            Boolean(value) => write!(f, "{value}"),
-            Integer(value) => write!(f, "{value}"),
-            Float(value) => write!(f, "{value}"),
-            String(value) => write!(f, "\"{value}\""),
+            Recovered::Parsed(value) => Ok(value),
+            Recovered::Error(error) => Err(error),
         }
     }
 }
-impl Parse for Literal {
+impl<T: Parse> Parse for Recovered<T> {
     fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
-        match tokens.pop().ok_or(ParseError::EndOfFile)?.as_str() {
-            "true" => Ok(Literal::Boolean(true)),
-            "false" => Ok(Literal::Boolean(false)),
-            "\"" => {
-                let mut string: String = String::new();
-                while tokens.last().ok_or(ParseError::EndOfFile)? != "\"" {
-                    string += &tokens.pop().unwrap();
-                }
-                ensure_tokens!(tokens, "\"");
-                Ok(Literal::String(string))
-            }
-            token => {
-                let mut token: String = token.to_string();
-                if let "+" | "-" = token.as_str() {
-                    token += &tokens.pop().ok_or(ParseError::EndOfFile)?;
-                }
-
-                if let Ok(int) = strtoint::strtoint(&token) {
-                    Ok(Literal::Integer(int))
-                } else if let Ok(float) = token.parse::<f64>() {
-                    Ok(Literal::Float(float))
-                } else {
-                    tokens.push(token.clone());
-                    Err(ParseError::InvalidToken {
-                        token: token.clone(),
-                        error: "Invalid literal".to_string(),
-                    })
-                }
+        match T::parse(tokens) {
+            Ok(value) => Ok(Recovered::Parsed(value)),
+            Err(error) => {
+                skip_to_sync(tokens, SYNC_TOKENS);
+                Ok(Recovered::Error(error))
             }
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::{test_parse, tokenize};
+/// A value that is parsed atomically.
+/// If parsing `T` fails, the tokens are restored to exactly how they were before parsing,
+/// so alternatives can be tried safely.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Atomic<T>(pub T);
+impl<T: Parse> Parse for Atomic<T> {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        parse_restoring(tokens, |result: &Result<T, ParseError>, _| result.is_ok()).map(Atomic)
+    }
+}
 
-    use super::*;
+/// An optional value, parsed leniently.
+/// If parsing `T` fails at any point, the tokens are restored and `None` is returned,
+/// so this never fails. This can hide real errors in a `T` that is present but malformed;
+/// use [`parse_committed`] to report those instead.
+impl<T: Parse> Parse for Option<T> {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        Ok(Atomic::<T>::parse(tokens).ok().map(|value| value.0))
+    }
+}
 
-    #[test]
-    fn test_identifier() {
-        let mut tokens: Vec<String> = tokenize!("cool_identifier");
+/// Parse an optional value with committed choice.
+/// If parsing `T` fails without consuming any tokens, the tokens are restored and
+/// `None` is returned, like with `Option<T>`. Once `T` has consumed at least one token,
+/// like a leading keyword, it is committed to, and a failure is returned as an error instead.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_committed<T: Parse>(tokens: &mut Vec<String>) -> Result<Option<T>, ParseError> {
+    let before: usize = tokens.len();
+    match parse_restoring(tokens, |result: &Result<T, ParseError>, consumed| {
+        result.is_ok() || consumed
+    }) {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if tokens.len() < before => Err(error),
+        Err(_) => Ok(None),
+    }
+}
 
-        test_parse!(
-            tokens,
-            Identifier,
-            Ok(Identifier("cool_identifier".to_string()))
-        );
+/// Parse a list of items separated by the given separator.
+/// See [`parse_separated_any`].
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_separated<T: Parse>(
+    tokens: &mut Vec<String>,
+    separator: &str,
+) -> Result<Vec<T>, ParseError> {
+    parse_separated_any(tokens, &[separator])
+}
 
-        assert!(tokens.is_empty());
-        test_parse!(tokens, Identifier, Err(ParseError::EndOfFile));
+/// Parse a list of items separated by the given separator, like [`parse_separated`],
+/// but requiring at least one item.
+/// If the first item fails to parse, its error is returned, with the context
+/// "Expected at least one `what`", like "Expected at least one parameter".
+/// This includes a leading separator, like in `, a`.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_separated_nonempty<T: Parse>(
+    tokens: &mut Vec<String>,
+    separator: &str,
+    what: &str,
+) -> Result<Vec<T>, ParseError> {
+    let Atomic(first) = Atomic::<T>::parse(tokens)
+        .map_err(|error| error.with_context(format!("Expected at least one {what}")))?;
+
+    let mut items: Vec<T> = vec![first];
+    if tokens.last().is_some_and(|token| token == separator) {
+        items.extend(parse_separated(tokens, separator)?);
     }
+    Ok(items)
+}
 
-    #[test]
-    fn test_literal() {
-        let mut tokens: Vec<String> = tokenize!("true false 0 +42 -5 123.0 +8.5 -11.4 \"string\"");
+/// Parse a list of items separated by any of the given separators, like `[",", "\n"]`.
+/// Consecutive separators are collapsed into one, and leading and trailing separators are allowed,
+/// so `a,\n\nb,` is the same as `a,b`.
+/// Parsing stops before the first token that is neither a separator nor the start of an item,
+/// so the caller should check what comes after the list, like a closing delimiter.
+///
+/// An item that fails at its first token is taken to be the end of the list, and is not consumed.
+/// An item that fails later, like `a = ;` for a key-value pair, is an error, which is returned
+/// with the tokens left where it occurred.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_separated_any<T: Parse>(
+    tokens: &mut Vec<String>,
+    separators: &[&str],
+) -> Result<Vec<T>, ParseError> {
+    let is_separator = |tokens: &Vec<String>| {
+        tokens
+            .last()
+            .is_some_and(|token| separators.contains(&token.as_str()))
+    };
 
-        test_parse!(tokens, Literal, Ok(Literal::Boolean(true)));
-        test_parse!(tokens, Literal, Ok(Literal::Boolean(false)));
-        test_parse!(tokens, Literal, Ok(Literal::Integer(0)));
-        test_parse!(tokens, Literal, Ok(Literal::Integer(42)));
-        test_parse!(tokens, Literal, Ok(Literal::Integer(-5)));
-        test_parse!(tokens, Literal, Ok(Literal::Float(123.0)));
-        test_parse!(tokens, Literal, Ok(Literal::Float(8.5)));
-        test_parse!(tokens, Literal, Ok(Literal::Float(-11.4)));
-        test_parse!(tokens, Literal, Ok(Literal::String("string".to_string())));
+    let mut items: Vec<T> = vec![];
+    loop {
+        while is_separator(tokens) {
+            tokens.pop();
+        }
+        let Some(first) = tokens.last().cloned() else {
+            return Ok(items);
+        };
+        let at_first = |error: &ParseError| error.found_token() == Some(first.as_str());
+        match parse_restoring(tokens, |result: &Result<T, ParseError>, _| {
+            result.as_ref().err().is_none_or(|error| !at_first(error))
+        }) {
+            Ok(item) => items.push(item),
+            Err(error) if at_first(&error) => return Ok(items),
+            Err(error) => return Err(error),
+        }
+        if !is_separator(tokens) {
+            return Ok(items);
+        }
+    }
+}
 
-        assert!(tokens.is_empty());
-        test_parse!(tokens, Literal, Err(ParseError::EndOfFile));
+/// Parse a delimited list, like `[a, b, c]` with `open = "["`, `separator = ","`, and `close = "]"`.
+/// The list may be empty. If `allow_trailing` is set, a separator may come right before `close`.
+///
+/// After each item, a token that is neither `separator` nor `close` is an error naming both,
+/// running out of tokens is [`ParseError::EndOfFile`] saying what was expected,
+/// and a disallowed trailing separator has its own error.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_delimited_list<T: Parse>(
+    tokens: &mut Vec<String>,
+    open: &str,
+    separator: &str,
+    close: &str,
+    allow_trailing: bool,
+) -> Result<Vec<T>, ParseError> {
+    ensure_tokens!(tokens, open);
+    let items: Vec<T> = parse_list(
+        tokens,
+        &[ListEnd::Token(close)],
+        separator,
+        Separators::Between { allow_trailing },
+    )?;
+    ensure_tokens!(tokens, close);
+    Ok(items)
+}
+
+/// Where a list of items ends, for [`parse_until`], [`parse_statements`], and [`parse_delimited_list`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListEnd<'a> {
+    /// The list ends before the given token, like `}` at the end of a block.
+    Token(&'a str),
+    /// The list ends when the tokens run out, like the items of a whole file.
+    EndOfFile,
+}
+impl ListEnd<'_> {
+    /// Whether the next token ends the list.
+    pub fn is_reached(&self, tokens: &[String]) -> bool {
+        match self {
+            ListEnd::Token(sentinel) => tokens.last().is_some_and(|token| token == sentinel),
+            ListEnd::EndOfFile => tokens.is_empty(),
+        }
+    }
+}
+impl Display for ListEnd<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListEnd::Token(sentinel) => write!(f, "{sentinel}"),
+            ListEnd::EndOfFile => write!(f, "end of file"),
+        }
+    }
+}
+
+/// Parse items separated by `separator` until `end` is reached, so the same grammar works for
+/// the body of a block like `{ a; b; }`, with [`ListEnd::Token`], and for a whole file,
+/// with [`ListEnd::EndOfFile`]. A trailing separator is allowed.
+///
+/// The end token is not consumed, so the caller can check it. When the list ends at a token,
+/// running out of tokens first is [`ParseError::EndOfFile`] saying what was expected.
+/// When it ends at the end of the file, any token after an item other than `separator` is an error.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_until<T: Parse>(
+    tokens: &mut Vec<String>,
+    end: ListEnd,
+    separator: &str,
+) -> Result<Vec<T>, ParseError> {
+    parse_list(
+        tokens,
+        &[end],
+        separator,
+        Separators::Between {
+            allow_trailing: true,
+        },
+    )
+}
+
+/// Where separators go in a list parsed by [`parse_list`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Separators {
+    /// Separators go between items, like `a, b`, optionally with one before the end.
+    Between { allow_trailing: bool },
+    /// Every item is followed by a separator, like `a; b;`. Repeated separators are skipped.
+    After,
+}
+
+/// Parse items until one of `ends` is reached. The end token is not consumed.
+/// Running out of tokens when the list can't end there is [`ParseError::EndOfFile`]
+/// saying what was expected.
+fn parse_list<T: Parse>(
+    tokens: &mut Vec<String>,
+    ends: &[ListEnd],
+    separator: &str,
+    separators: Separators,
+) -> Result<Vec<T>, ParseError> {
+    let is_end = |tokens: &[String]| ends.iter().any(|end| end.is_reached(tokens));
+    let expected_end: String = ends
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>()
+        .join(" or ");
+    let expected: String = match separators {
+        Separators::Between { .. } => format!("{separator} or {expected_end}"),
+        Separators::After => separator.to_string(),
+    };
+
+    let mut items: Vec<T> = vec![];
+    loop {
+        if separators == Separators::After {
+            while parse_optional_keyword(tokens, separator) {}
+        }
+        if is_end(tokens) {
+            return Ok(items);
+        }
+        ensure!(!tokens.is_empty(), ParseError::end_of_file(&expected_end));
+        items.push(T::parse(tokens)?);
+
+        if separators != Separators::After && is_end(tokens) {
+            return Ok(items);
+        }
+        let token: &String = tokens
+            .last()
+            .ok_or_else(|| ParseError::end_of_file(&expected))?;
+        ensure!(token == separator, ParseError::expected(token, &expected));
+        tokens.pop();
+
+        if separators
+            == (Separators::Between {
+                allow_trailing: false,
+            })
+            && is_end(tokens)
+        {
+            let end: &str = tokens.last().map_or("end of file", String::as_str);
+            return Err(ParseError::invalid(
+                tokens.last().map_or(separator, String::as_str),
+                format!("Trailing {separator} is not allowed before {end}"),
+            ));
+        }
+    }
+}
+
+/// Parse items separated by `separator` until `sentinel` is reached, like the body of `{ a; b; }`.
+/// The sentinel is not consumed, so the caller can check it. A trailing separator is allowed.
+/// Running out of tokens before the sentinel is [`ParseError::EndOfFile`] saying what was expected.
+/// This is [`parse_until`] with [`ListEnd::Token`].
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_terminated<T: Parse>(
+    tokens: &mut Vec<String>,
+    sentinel: &str,
+    separator: &str,
+) -> Result<Vec<T>, ParseError> {
+    parse_until(tokens, ListEnd::Token(sentinel), separator)
+}
+
+/// Parse operands separated by binary operators, grouping them from the left.
+/// `a - b - c` is combined as `(a - b) - c`.
+///
+/// Each operator is consumed only if it is one of `operators`, so other tokens end the expression.
+/// For precedence, parse the operands of a lower-precedence level with a higher-precedence one,
+/// like `+` and `-` over `*` and `/`. See `examples/expression.rs` for a full example.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_binary_left<T>(
+    tokens: &mut Vec<String>,
+    parse_operand: impl Fn(&mut Vec<String>) -> Result<T, ParseError>,
+    operators: &[&str],
+    combine: impl Fn(T, &str, T) -> T,
+) -> Result<T, ParseError> {
+    let mut left: T = parse_operand(tokens)?;
+    while let Some(operator) = tokens
+        .last()
+        .and_then(|token| operators.iter().find(|operator| *operator == token))
+    {
+        tokens.pop();
+        let right: T = parse_operand(tokens)?;
+        left = combine(left, operator, right);
+    }
+    Ok(left)
+}
+
+/// Parse a value if present, otherwise use its default.
+/// If parsing fails, the tokens are restored and `T::default()` is returned.
+#[must_use = "the parsed value is lost if the result is ignored"]
+pub fn parse_or_default<T: Parse + Default>(tokens: &mut Vec<String>) -> T {
+    Atomic::<T>::parse(tokens).map_or_else(|_| T::default(), |value| value.0)
+}
+
+/// Parse a single token with its `FromStr` impl.
+/// This bridges types that already know how to parse themselves from a string.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_from_str<T>(tokens: &mut Vec<String>) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let token: String = tokens.pop().ok_or_else(ParseError::eof)?;
+    token
+        .parse::<T>()
+        .map_err(|error| ParseError::invalid(token, error.to_string()))
+}
+
+/// Implement `Parse` with [`parse_from_str`].
+macro_rules! impl_parse_from_str {
+    ($($ty:ty),+) => {
+        $(
+            impl Parse for $ty {
+                fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                    parse_from_str(tokens)
+                }
+            }
+        )+
+    };
+}
+// IPv6 socket addresses, like `[::1]:25565`, can't be parsed, since `[` and `]` are separate tokens.
+impl_parse_from_str!(IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr);
+
+/// Pop a number token, merging a leading `+` or `-` token into it like [`Literal`] does.
+/// Returns the sign, if there was one, and the number token as written in the source.
+fn pop_signed(tokens: &mut Vec<String>) -> Result<(&'static str, String), ParseError> {
+    let token: String = tokens
+        .pop()
+        .ok_or_else(|| ParseError::end_of_file("number"))?;
+    let sign: &'static str = match token.as_str() {
+        "+" => "+",
+        "-" => "-",
+        _ => return Ok(("", token)),
+    };
+    Ok((
+        sign,
+        tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("number"))?,
+    ))
+}
+
+/// The error for an integer token that doesn't fit the target type, or isn't an integer at all.
+fn integer_error(signed: &str, number: String, name: &str) -> ParseError {
+    let digits: &str = ["0x", "0o", "0b"]
+        .iter()
+        .find_map(|prefix| number.strip_prefix(prefix))
+        .unwrap_or(&number);
+    let is_integer: bool = parse_integer::<i128>(signed).is_some()
+        || parse_integer::<u128>(signed).is_some()
+        || (digits.starts_with(|c: char| c.is_ascii_digit())
+            && digits.chars().all(|c| c.is_ascii_hexdigit() || c == '_'));
+    if is_integer {
+        ParseError::invalid(number, format!("Integer out of range for {name}"))
+    } else {
+        ParseError::invalid(number, "Expected integer")
+    }
+}
+
+/// Implement `Parse` for integer types, by parsing an integer literal directly into the type,
+/// so the whole range of types wider than `isize` is supported.
+/// Errors report the number token as written, without the sign, so they can be located.
+macro_rules! impl_parse_integer {
+    ($($ty:ty),+) => {
+        $(
+            impl Parse for $ty {
+                fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                    let (sign, number) = pop_signed(tokens)?;
+                    check_radix_digits(&number)?;
+                    let signed: String = format!("{sign}{number}");
+                    // `-0` is zero, even for unsigned types.
+                    let zero = || (sign == "-").then(|| parse_integer::<$ty>(&number)).flatten();
+                    match parse_integer::<$ty>(&signed).or_else(|| zero().filter(|value| *value == 0)) {
+                        Some(value) => Ok(value),
+                        None => Err(integer_error(&signed, number, stringify!($ty))),
+                    }
+                }
+            }
+        )+
+    };
+}
+impl_parse_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Implement `Parse` for nonzero integer types, by parsing the integer type and checking it isn't zero.
+macro_rules! impl_parse_nonzero {
+    ($($ty:ty => $integer:ty),+) => {
+        $(
+            impl Parse for $ty {
+                fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                    // The number token, after any sign, so the error points at `0x0` as written.
+                    let number: String = match tokens.last().map(String::as_str) {
+                        Some("+" | "-") => tokens.iter().rev().nth(1),
+                        _ => tokens.last(),
+                    }
+                    .cloned()
+                    .unwrap_or_default();
+                    <$ty>::new(<$integer>::parse(tokens)?)
+                        .ok_or_else(|| ParseError::invalid(number, "Integer must be nonzero"))
+                }
+            }
+        )+
+    };
+}
+impl_parse_nonzero!(
+    NonZeroI8 => i8,
+    NonZeroI16 => i16,
+    NonZeroI32 => i32,
+    NonZeroI64 => i64,
+    NonZeroI128 => i128,
+    NonZeroIsize => isize,
+    NonZeroU8 => u8,
+    NonZeroU16 => u16,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64,
+    NonZeroU128 => u128,
+    NonZeroUsize => usize
+);
+
+/// Implement `Parse` for float types, by parsing a numeric [`Literal`].
+/// Integers are accepted too, so `1` parses as `1.0`.
+macro_rules! impl_parse_float {
+    ($($ty:ty),+) => {
+        $(
+            impl Parse for $ty {
+                fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                    // Errors report the number token as written, without the sign.
+                    let sign: bool = tokens.last().is_some_and(|token| token == "+" || token == "-");
+                    let token: String = peek_n(tokens, sign as usize).cloned().unwrap_or_default();
+                    let value: $ty = match Literal::parse(tokens)? {
+                        Literal::Integer(value) => value as $ty,
+                        Literal::Float(value) => value as $ty,
+                        _ => return Err(ParseError::invalid(token, "Expected number")),
+                    };
+                    ensure!(
+                        value.is_finite(),
+                        ParseError::invalid(
+                            token,
+                            concat!("Number out of range for ", stringify!($ty)),
+                        )
+                    );
+                    Ok(value)
+                }
+            }
+        )+
+    };
+}
+impl_parse_float!(f32, f64);
+
+/// The time units a [`Duration`] can be written in, and how many nanoseconds each is.
+const TIME_UNITS: &[(&str, u64)] = &[
+    ("ns", 1),
+    ("us", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60_000_000_000),
+];
+
+/// A wall-clock duration, like `500ms`, `2s`, or `1.5m`.
+/// The unit is required, and can be `ns`, `us`, `ms`, `s`, or `m` for minutes.
+/// Whole numbers are exact, and fractions are rounded to the nearest nanosecond.
+/// For durations in game ticks, see [`crate::minecraft::TickDuration`].
+impl Parse for Duration {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("duration"))?;
+        let split: usize = token
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .len();
+        let (number, unit) = token.split_at(split);
+
+        ensure!(
+            number.starts_with(|c: char| c.is_ascii_digit() || c == '.'),
+            ParseError::invalid(&token, "Invalid duration")
+        );
+        let units: Vec<&str> = TIME_UNITS.iter().map(|(unit, _)| *unit).collect();
+        let nanos_per_unit: u64 = match TIME_UNITS.iter().find(|(other, _)| *other == unit) {
+            Some((_, nanos)) => *nanos,
+            None if unit.is_empty() => {
+                return Err(ParseError::invalid(
+                    &token,
+                    format!("Missing time unit, expected one of {}", units.join(", ")),
+                ))
+            }
+            None => {
+                return Err(ParseError::invalid(
+                    &token,
+                    format!(
+                        "Unknown time unit `{unit}`, expected one of {}",
+                        units.join(", ")
+                    ),
+                ))
+            }
+        };
+
+        let out_of_range = || ParseError::invalid(&token, "Duration out of range");
+        if let Ok(whole) = number.parse::<u64>() {
+            let nanos: u128 = whole as u128 * nanos_per_unit as u128;
+            let seconds: u64 = u64::try_from(nanos / 1_000_000_000).map_err(|_| out_of_range())?;
+            return Ok(Duration::new(seconds, (nanos % 1_000_000_000) as u32));
+        }
+        match number.parse::<f64>() {
+            Ok(value) => Duration::try_from_secs_f64(value * nanos_per_unit as f64 / 1e9)
+                .map_err(|_| out_of_range()),
+            Err(_) => Err(ParseError::invalid(&token, "Invalid duration")),
+        }
+    }
+}
+
+/// The empty production.
+/// This never consumes any tokens and always succeeds.
+impl Parse for () {
+    fn parse(_tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        Ok(())
+    }
+}
+
+/// A function that parses an object from a list of tokens.
+pub type ParseFn<T> = fn(&mut Vec<String>) -> Result<T, ParseError>;
+
+/// A data-driven way to parse enums, by dispatching on a leading keyword.
+/// Implement `Parse` by calling [`ParseEnum::parse_enum`].
+pub trait ParseEnum
+where
+    Self: Sized + 'static,
+{
+    /// The keyword of each variant, along with how to parse the rest of the variant after the keyword.
+    fn variants() -> &'static [(&'static str, ParseFn<Self>)];
+
+    /// Parse a variant by consuming its keyword and dispatching to its parser.
+    /// If the next token is not a known keyword, [`ParseError::ExpectedOneOf`] is returned.
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    fn parse_enum(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: &String = tokens.last().ok_or_else(|| {
+            let keywords: Vec<&str> = Self::variants()
+                .iter()
+                .map(|(keyword, _)| *keyword)
+                .collect();
+            ParseError::end_of_file(&format!("one of {}", keywords.join(", ")))
+        })?;
+        match Self::variants()
+            .iter()
+            .find(|(keyword, _)| keyword == token)
+        {
+            Some((_, parse)) => {
+                tokens.pop();
+                parse(tokens)
+            }
+            None => Err(ParseError::ExpectedOneOf {
+                token: token.clone(),
+                expected: Self::variants()
+                    .iter()
+                    .map(|(keyword, _)| keyword.to_string())
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// An identifier.
+/// The identifier must not be empty or start with a number, and can only contain letters, numbers, and underscores.
+/// Other names can be quoted with backticks, like `` `my name` ``. Inside backticks,
+/// `` \` `` is a backtick and `\\` is a backslash, like escapes in string literals.
+/// Quoted identifiers that would be valid unquoted are the same as the unquoted identifier.
+///
+/// There are no reserved words, so `true` and `false` are valid identifiers.
+/// [`Literal`] always parses them as booleans, so where both are allowed,
+/// whichever is parsed first takes precedence. Parse an `Identifier` to treat them as names.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Identifier(pub String);
+impl Identifier {
+    /// The identifier as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Parse an identifier and intern it, so repeated identifiers share one string.
+    /// Validation is the same as for [`Identifier::parse`].
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    pub fn parse_interned(
+        tokens: &mut Vec<String>,
+        interner: &mut Interner,
+    ) -> Result<Symbol, ParseError> {
+        Identifier::parse(tokens).map(|identifier| interner.intern(&identifier))
+    }
+
+    /// Parse an identifier and normalize it, keeping the original spelling too.
+    /// Validation is the same as for [`Identifier::parse`], and happens before normalizing.
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    pub fn parse_canonical(
+        tokens: &mut Vec<String>,
+        form: CanonForm,
+    ) -> Result<CanonicalIdentifier, ParseError> {
+        let original: Identifier = Identifier::parse(tokens)?;
+        Ok(CanonicalIdentifier {
+            canonical: form.apply(&original),
+            original,
+        })
+    }
+}
+impl AsRef<str> for Identifier {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+impl Deref for Identifier {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+impl PartialEq<str> for Identifier {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+impl PartialEq<&str> for Identifier {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+impl From<Identifier> for String {
+    fn from(identifier: Identifier) -> Self {
+        identifier.0
+    }
+}
+impl Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if identifier_error(&self.0).is_none() {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "`{}`", self.0.replace('\\', "\\\\").replace('`', "\\`"))
+        }
+    }
+}
+impl Parse for Identifier {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let value: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("identifier"))?;
+        if value.starts_with('`') {
+            return unquote_identifier(&value)
+                .map(Identifier)
+                .map_err(|error| ParseError::invalid(value, error));
+        }
+
+        match identifier_error(&value) {
+            Some(error) => Err(ParseError::invalid(value, error)),
+            None => Ok(Identifier(value)),
+        }
+    }
+}
+
+/// Why the given text is not a valid unquoted identifier, if it isn't.
+fn identifier_error(value: &str) -> Option<&'static str> {
+    // Check every byte in one pass. Any non-ASCII byte is invalid,
+    // so this doesn't need to decode chars.
+    match value.as_bytes() {
+        [] => Some("Empty identifier"),
+        [first, ..] if !(first.is_ascii_alphabetic() || *first == b'_') => Some(
+            "Identifiers must not start with a number and can only contain letters, numbers, and underscores",
+        ),
+        [_, rest @ ..] => rest
+            .iter()
+            .any(|c| !(c.is_ascii_alphanumeric() || *c == b'_'))
+            .then_some("Identifiers can only contain letters, numbers, and underscores"),
+    }
+}
+
+/// Decode a backtick-quoted identifier, including its backticks.
+fn unquote_identifier(quoted: &str) -> Result<String, String> {
+    let mut identifier: String = String::new();
+    let mut chars = quoted.chars().skip(1);
+
+    while let Some(c) = chars.next() {
+        match c {
+            '`' if identifier.is_empty() => return Err("Empty identifier".to_string()),
+            '`' => return Ok(identifier),
+            '\\' => match chars.next() {
+                Some(escape @ ('`' | '\\')) => identifier.push(escape),
+                Some(escape) => {
+                    return Err(format!("Unknown escape `\\{escape}` in quoted identifier"))
+                }
+                None => break,
+            },
+            c => identifier.push(c),
+        }
+    }
+
+    Err("Unterminated quoted identifier".to_string())
+}
+
+/// How [`Identifier::parse_canonical`] normalizes an identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CanonForm {
+    /// All letters lowercase, so `MyName` is `myname`.
+    Lowercase,
+    /// Lowercase words separated by single underscores, so `myName`, `MyName`, and `my__name`
+    /// are all `my_name`. Leading and trailing underscores are kept.
+    SnakeCase,
+}
+impl CanonForm {
+    /// Normalize a valid identifier.
+    pub fn apply(self, identifier: &str) -> String {
+        match self {
+            CanonForm::Lowercase => identifier.to_ascii_lowercase(),
+            CanonForm::SnakeCase => {
+                let trimmed: &str = identifier.trim_matches('_');
+                if trimmed.is_empty() {
+                    return identifier.to_string();
+                }
+                let leading: usize = identifier.len() - identifier.trim_start_matches('_').len();
+                let trailing: usize = identifier.len() - identifier.trim_end_matches('_').len();
+
+                let mut words: String = String::new();
+                let mut previous: Option<char> = None;
+                for c in trimmed.chars() {
+                    if c == '_' {
+                        if previous != Some('_') {
+                            words.push('_');
+                        }
+                    } else {
+                        if c.is_ascii_uppercase()
+                            && previous
+                                .is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+                        {
+                            words.push('_');
+                        }
+                        words.push(c.to_ascii_lowercase());
+                    }
+                    previous = Some(c);
+                }
+                "_".repeat(leading) + &words + &"_".repeat(trailing)
+            }
+        }
+    }
+}
+
+/// An identifier normalized with a [`CanonForm`], from [`Identifier::parse_canonical`].
+///
+/// [`CanonicalIdentifier::canonical`] is the normalized form, which is used for equality and
+/// hashing, so `MyName` and `myname` are equal in [`CanonForm::Lowercase`].
+/// [`CanonicalIdentifier::original`] is the spelling as written, which is what is displayed,
+/// so the output round-trips.
+#[derive(Debug, Clone)]
+pub struct CanonicalIdentifier {
+    canonical: String,
+    original: Identifier,
+}
+impl CanonicalIdentifier {
+    /// The normalized form, used for comparisons.
+    pub fn canonical(&self) -> &str {
+        &self.canonical
+    }
+
+    /// The identifier as it was written.
+    pub fn original(&self) -> &Identifier {
+        &self.original
+    }
+}
+impl PartialEq for CanonicalIdentifier {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical == other.canonical
+    }
+}
+impl Eq for CanonicalIdentifier {}
+impl std::hash::Hash for CanonicalIdentifier {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.canonical.hash(state);
+    }
+}
+impl PartialEq<str> for CanonicalIdentifier {
+    fn eq(&self, other: &str) -> bool {
+        self.canonical == other
+    }
+}
+impl Display for CanonicalIdentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.original)
+    }
+}
+
+/// An identifier that may start with a number, like `1x` or `2a` in version components.
+/// This relaxes the "must not start with a number" rule of [`Identifier`], but keeps the rest:
+/// the identifier must not be empty, and can only contain letters, numbers, and underscores.
+/// Numbers on their own, like `12`, are also accepted.
+/// Backtick-quoted names work like they do for [`Identifier`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LenientIdentifier(pub String);
+impl LenientIdentifier {
+    /// Whether the text is a valid unquoted lenient identifier.
+    fn is_valid(value: &str) -> bool {
+        !value.is_empty()
+            && value
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'_')
+    }
+}
+impl From<Identifier> for LenientIdentifier {
+    fn from(identifier: Identifier) -> Self {
+        LenientIdentifier(identifier.0)
+    }
+}
+impl Display for LenientIdentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if LenientIdentifier::is_valid(&self.0) {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "{}", Identifier(self.0.clone()))
+        }
+    }
+}
+impl Parse for LenientIdentifier {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let value: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("identifier"))?;
+        if value.starts_with('`') {
+            return unquote_identifier(&value)
+                .map(LenientIdentifier)
+                .map_err(|error| ParseError::invalid(value, error));
+        }
+
+        ensure!(
+            LenientIdentifier::is_valid(&value),
+            ParseError::invalid(
+                value,
+                "Identifiers can only contain letters, numbers, and underscores"
+            )
+        );
+        Ok(LenientIdentifier(value))
+    }
+}
+
+/// A dotted path of identifiers, like `a.b.c`.
+/// The path must contain at least one identifier, and must not end with a dot.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Path(pub Vec<Identifier>);
+impl Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let segments: Vec<String> = self.0.iter().map(Identifier::to_string).collect();
+        write!(f, "{}", segments.join("."))
+    }
+}
+impl Parse for Path {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let mut segments: Vec<Identifier> = vec![Identifier::parse(tokens)?];
+
+        while tokens.last().is_some_and(|token| token == ".") {
+            tokens.pop();
+            segments.push(Identifier::parse(tokens).map_err(|error| match error {
+                ParseError::InvalidToken { token, .. } => {
+                    ParseError::invalid(token, "Expected identifier after `.` in path")
+                }
+                error => error,
+            })?);
+        }
+
+        Ok(Path(segments))
+    }
+}
+
+/// A subscript, like `[0]`.
+/// The index can be negative, like `[-1]`, to count from the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Subscript(pub isize);
+impl Display for Subscript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]", self.0)
+    }
+}
+impl Parse for Subscript {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        ensure_tokens!(tokens, "[");
+        let index: isize = isize::parse(tokens)?;
+        ensure_tokens!(tokens, "]");
+        Ok(Subscript(index))
+    }
+}
+
+/// A percentage, like `50%` or `12.5%`, stored as a fraction, so `50%` is `0.5`.
+/// `%` is a special token, so the number and the `%` are separate tokens,
+/// and `50 %` is also accepted.
+/// It is displayed with the fewest decimals that parse back to the same fraction,
+/// so `7%` is displayed as `7%`, even though `0.07 * 100.0` is not exactly 7.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Percent(pub f64);
+impl Display for Percent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent: f64 = self.0 * 100.0;
+        let shortest: Option<String> = (0..=17)
+            .map(|precision| format!("{percent:.precision$}"))
+            .find(|text| {
+                text.parse::<f64>()
+                    .is_ok_and(|value| value / 100.0 == self.0)
+            });
+        match shortest {
+            Some(text) => write!(f, "{text}%"),
+            None => write!(f, "{percent}%"),
+        }
+    }
+}
+impl Parse for Percent {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let value: f64 = f64::parse(tokens)?;
+        ensure_tokens!(tokens, "%");
+        Ok(Percent(value / 100.0))
+    }
+}
+
+/// A `key=value` pair.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyValue<K, V> {
+    pub key: K,
+    pub value: V,
+}
+impl<K: Display, V: Display> Display for KeyValue<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+impl<K: Parse, V: Parse> Parse for KeyValue<K, V> {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let key: K = K::parse(tokens)?;
+        ensure_tokens!(tokens, "=");
+        let value: V = V::parse(tokens)?;
+        Ok(KeyValue { key, value })
+    }
+}
+
+/// Consume a `:`, like the one after a key in `key: value`.
+///
+/// `:` is not a special character, so `minecraft:stone` stays one token for
+/// [`crate::minecraft::ResourceLocation`]. Instead, this handles a colon glued to the start of
+/// the next token, like the `:value` left over from splitting `key:value`, and puts the rest back.
+#[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+pub fn parse_colon(tokens: &mut Vec<String>) -> Result<(), ParseError> {
+    let token: String = tokens.pop().ok_or_else(|| ParseError::end_of_file(":"))?;
+    match token.strip_prefix(':') {
+        Some("") => {}
+        Some(rest) => tokens.push(rest.to_string()),
+        None => return Err(ParseError::expected(token, ":")),
+    }
+    Ok(())
+}
+
+/// A `key: value` pair, like in JSON or YAML.
+/// The key and value may be written with or without spaces, so `key:value`, `key: value`,
+/// and `key : value` are all accepted. Only the first `:` splits, so the value can contain
+/// more, like `block:minecraft:stone`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ColonPair<V> {
+    pub key: Identifier,
+    pub value: V,
+}
+impl<V: Display> Display for ColonPair<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.value)
+    }
+}
+impl<V: Parse> Parse for ColonPair<V> {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let token: String = tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("identifier"))?;
+        match token.split_once(':') {
+            Some((key, rest)) => {
+                tokens.push(format!(":{rest}"));
+                tokens.push(key.to_string());
+            }
+            None => tokens.push(token),
+        }
+
+        let key: Identifier = Identifier::parse(tokens)?;
+        parse_colon(tokens)?;
+        let value: V = V::parse(tokens)?;
+        Ok(ColonPair { key, value })
+    }
+}
+
+/// One or more flags separated by `|`, like `FLAG_A | FLAG_B`.
+/// Only a single `|` separates flags. `||` is a separate token, and ends the flags.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Flags<T>(pub Vec<T>);
+impl<T: Display> Display for Flags<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags: Vec<String> = self.0.iter().map(T::to_string).collect();
+        write!(f, "{}", flags.join(" | "))
+    }
+}
+impl<T: Parse> Parse for Flags<T> {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        let mut flags: Vec<T> = vec![T::parse(tokens)?];
+        while parse_optional_keyword(tokens, "|") {
+            flags.push(T::parse(tokens)?);
+        }
+        Ok(Flags(flags))
+    }
+}
+
+/// Decode a string literal, including its quotes.
+/// On failure, returns an error message naming the offending escape and its byte index in the literal.
+pub(crate) fn unescape_string(literal: &str) -> Result<String, String> {
+    let mut string: String = String::new();
+    let mut chars = literal.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok(string),
+            '\\' => {}
+            c => {
+                string.push(c);
+                continue;
+            }
+        }
+
+        let escape: char = match chars.next() {
+            Some((_, escape)) => escape,
+            None => break,
+        };
+        string.push(match escape {
+            '\\' => '\\',
+            '"' => '"',
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'x' => {
+                let digits: String = literal[i + 2..].chars().take(2).collect();
+                ensure!(
+                    digits.len() == 2 && digits.chars().all(|c| c.is_ascii_hexdigit()),
+                    format!(
+                        "Escape `\\x` at index {i} must be followed by exactly two hex digits"
+                    )
+                );
+                let byte: u8 = u8::from_str_radix(&digits, 16).unwrap();
+                ensure!(
+                    byte.is_ascii(),
+                    format!("Escape `\\x{digits}` at index {i} is not ASCII, use `\\u{{...}}` instead")
+                );
+                chars.nth(1);
+                char::from(byte)
+            }
+            'u' => {
+                let code: Option<&str> = literal[i + 2..]
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.split_once('}'))
+                    .map(|(code, _)| code);
+                let Some(code) = code else {
+                    return Err(format!(
+                        "Escape `\\u` at index {i} must be followed by a code point in braces, like `\\u{{1F600}}`"
+                    ));
+                };
+                let is_hex: bool = code.chars().all(|c| c.is_ascii_hexdigit());
+                let c: Option<char> = (!code.is_empty() && code.len() <= 6 && is_hex)
+                    .then(|| char::from_u32(u32::from_str_radix(code, 16).unwrap()))
+                    .flatten();
+                let Some(c) = c else {
+                    return Err(format!(
+                        "Invalid code point `\\u{{{code}}}` at index {i} in string literal"
+                    ));
+                };
+                chars.nth(code.len() + 1);
+                c
+            }
+            escape => return Err(format!("Unknown escape `\\{escape}` at index {i}")),
+        });
+    }
+
+    Err("Unterminated string literal".to_string())
+}
+
+/// Escape a string so it can be written back as a string literal.
+fn escape_string(string: &str) -> String {
+    let mut escaped: String = String::new();
+    for c in string.chars() {
+        match c {
+            '\\' => escaped += "\\\\",
+            '"' => escaped += "\\\"",
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '\r' => escaped += "\\r",
+            '\0' => escaped += "\\0",
+            c if c.is_control() => escaped += &format!("\\u{{{:X}}}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A literal value.
+/// This can be a boolean, integer, float, or string.
+///
+/// `true` and `false` are always parsed as booleans, never as identifiers.
+///
+/// Integers that don't fit in an `isize` are parsed as floats.
+/// Floats must be finite: `inf`, `nan`, and literals that overflow `f64` are rejected.
+/// Negative zero is normalized to positive zero.
+///
+/// Strings support the escapes `\\`, `\"`, `\n`, `\t`, `\r`, `\0`,
+/// `\xNN` with exactly two hex digits for an ASCII character, up to `\x7F`,
+/// and `\u{...}` with a unicode code point.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Boolean(bool),
+    Integer(isize),
+    Float(f64),
+    String(String),
+}
+impl Literal {
+    /// A readable name for the kind of literal, for error messages like
+    /// "expected integer, found string".
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Literal::Boolean(_) => "boolean",
+            Literal::Integer(_) => "integer",
+            Literal::Float(_) => "float",
+            Literal::String(_) => "string",
+        }
+    }
+
+    /// The value of a boolean literal.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Literal::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer literal.
+    pub fn as_integer(&self) -> Option<isize> {
+        match self {
+            Literal::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of a numeric literal as a float.
+    /// Integers are converted, like in [`Literal::add`].
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Literal::Integer(value) => Some(*value as f64),
+            Literal::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of a string literal, without quotes or escapes.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Literal::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Whether the literal counts as true when used as a condition.
+    /// Booleans are themselves, numbers are truthy unless they are zero,
+    /// and strings are truthy unless they are empty.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Literal::Boolean(value) => *value,
+            Literal::Integer(value) => *value != 0,
+            Literal::Float(value) => *value != 0.0,
+            Literal::String(value) => !value.is_empty(),
+        }
+    }
+
+    /// Logical and of two literals, using [`Literal::is_truthy`].
+    pub fn and(&self, other: &Literal) -> Literal {
+        Literal::Boolean(self.is_truthy() && other.is_truthy())
+    }
+
+    /// Logical or of two literals, using [`Literal::is_truthy`].
+    pub fn or(&self, other: &Literal) -> Literal {
+        Literal::Boolean(self.is_truthy() || other.is_truthy())
+    }
+
+    /// Logical not of a literal, using [`Literal::is_truthy`].
+    pub fn not(&self) -> Literal {
+        Literal::Boolean(!self.is_truthy())
+    }
+
+    /// Add two literals. Numbers are added, and strings are concatenated.
+    ///
+    /// Numbers follow the same rules as parsing:
+    /// - Two integers give an integer, unless the result overflows an `isize`,
+    ///   in which case the operation is done on floats instead.
+    /// - An integer and a float give a float.
+    /// - Float results must be finite, and negative zero is normalized to positive zero.
+    /// - Booleans, and strings mixed with other literals, are errors.
+    pub fn add(&self, other: &Literal) -> Result<Literal, ParseError> {
+        if let (Literal::String(a), Literal::String(b)) = (self, other) {
+            return Ok(Literal::String(format!("{a}{b}")));
+        }
+        self.arithmetic("+", other, isize::checked_add, |a, b| a + b)
+    }
+
+    /// Subtract two numeric literals.
+    /// Numbers are promoted like in [`Literal::add`].
+    pub fn sub(&self, other: &Literal) -> Result<Literal, ParseError> {
+        self.arithmetic("-", other, isize::checked_sub, |a, b| a - b)
+    }
+
+    /// Multiply two numeric literals.
+    /// Numbers are promoted like in [`Literal::add`].
+    pub fn mul(&self, other: &Literal) -> Result<Literal, ParseError> {
+        self.arithmetic("*", other, isize::checked_mul, |a, b| a * b)
+    }
+
+    /// Divide two numeric literals. Dividing by zero is an error.
+    /// Integer division rounds towards zero, so `7 / 2` is `3`.
+    /// Numbers are promoted like in [`Literal::add`].
+    pub fn div(&self, other: &Literal) -> Result<Literal, ParseError> {
+        ensure!(
+            !matches!(other, Literal::Integer(0))
+                && !matches!(other, Literal::Float(b) if *b == 0.0),
+            ParseError::invalid(format!("{self} / {other}"), "Division by zero")
+        );
+        self.arithmetic("/", other, isize::checked_div, |a, b| a / b)
+    }
+
+    /// Apply an arithmetic operator to two numeric literals, promoting them as needed.
+    fn arithmetic(
+        &self,
+        operator: &str,
+        other: &Literal,
+        integer: fn(isize, isize) -> Option<isize>,
+        float: fn(f64, f64) -> f64,
+    ) -> Result<Literal, ParseError> {
+        let error =
+            |message: &str| ParseError::invalid(format!("{self} {operator} {other}"), message);
+        let (a, b) = match (self, other) {
+            (Literal::Integer(a), Literal::Integer(b)) => {
+                if let Some(result) = integer(*a, *b) {
+                    return Ok(Literal::Integer(result));
+                }
+                (*a as f64, *b as f64)
+            }
+            (Literal::Integer(a), Literal::Float(b)) => (*a as f64, *b),
+            (Literal::Float(a), Literal::Integer(b)) => (*a, *b as f64),
+            (Literal::Float(a), Literal::Float(b)) => (*a, *b),
+            _ => {
+                return Err(error(&format!(
+                    "Operator {operator} needs numeric operands"
+                )))
+            }
+        };
+
+        let result: f64 = float(a, b);
+        ensure!(result.is_finite(), error("Result must be finite"));
+        Ok(Literal::Float(if result == 0.0 { 0.0 } else { result }))
+    }
+}
+impl Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Literal::*;
+        match self {
+            Boolean(value) => write!(f, "{value}"),
+            Integer(value) => write!(f, "{value}"),
+            // Debug formatting always includes a decimal point or exponent,
+            // so the output parses back as a float.
+            Float(value) => write!(f, "{value:?}"),
+            String(value) => write!(f, "\"{}\"", escape_string(value)),
+        }
+    }
+}
+impl Parse for Literal {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        match tokens
+            .pop()
+            .ok_or_else(|| ParseError::end_of_file("literal"))?
+            .as_str()
+        {
+            "true" => Ok(Literal::Boolean(true)),
+            "false" => Ok(Literal::Boolean(false)),
+            token if token.starts_with('"') => unescape_string(token)
+                .map(Literal::String)
+                .map_err(|error| ParseError::invalid(token, error)),
+            token => {
+                let mut token: String = token.to_string();
+                // The sign is merged into the number before it is parsed,
+                // so values without a positive counterpart, like `isize::MIN`, are parsed correctly.
+                if let "+" | "-" = token.as_str() {
+                    token += &tokens
+                        .pop()
+                        .ok_or_else(|| ParseError::end_of_file("number"))?;
+                }
+
+                if let Err(error) = check_radix_digits(&token) {
+                    tokens.push(token);
+                    return Err(error);
+                }
+
+                if let Some(int) = parse_integer(&token) {
+                    Ok(Literal::Integer(int))
+                } else if let Ok(float) = token.parse::<f64>() {
+                    if !float.is_finite() {
+                        tokens.push(token.clone());
+                        return Err(ParseError::invalid(token, "Float literals must be finite"));
+                    }
+                    Ok(Literal::Float(if float == 0.0 { 0.0 } else { float }))
+                } else {
+                    tokens.push(token.clone());
+                    Err(ParseError::invalid(token, "Invalid literal"))
+                }
+            }
+        }
+    }
+}
+
+/// A value: either a literal, or an identifier referring to something else.
+/// Literals are tried first, so `true` and `false` are booleans, not references.
+/// If neither matches, the error from parsing a literal is returned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Literal(Literal),
+    Reference(Identifier),
+}
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Literal(literal) => write!(f, "{literal}"),
+            Value::Reference(identifier) => write!(f, "{identifier}"),
+        }
+    }
+}
+impl Parse for Value {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        match Atomic::<Literal>::parse(tokens) {
+            Ok(literal) => Ok(Value::Literal(literal.0)),
+            Err(error) => Atomic::<Identifier>::parse(tokens)
+                .map(|identifier| Value::Reference(identifier.0))
+                .map_err(|_| error),
+        }
+    }
+}
+
+/// An argument of an [`Attribute`]: either a literal, like `"reason"`,
+/// or a named literal, like `since="1.20"`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeArgument {
+    Positional(Literal),
+    Named(KeyValue<Identifier, Literal>),
+}
+impl Display for AttributeArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttributeArgument::Positional(literal) => write!(f, "{literal}"),
+            AttributeArgument::Named(pair) => write!(f, "{pair}"),
+        }
+    }
+}
+impl Parse for AttributeArgument {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        if peek_n(tokens, 1).is_some_and(|token| token == "=") {
+            KeyValue::parse(tokens).map(AttributeArgument::Named)
+        } else {
+            Literal::parse(tokens).map(AttributeArgument::Positional)
+        }
+    }
+}
+
+/// An attribute before a declaration, like `@deprecated` or `@since("1.20", note="x")`.
+/// The arguments are optional, and `@name()` is the same as `@name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: Identifier,
+    pub arguments: Vec<AttributeArgument>,
+}
+impl Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if !self.arguments.is_empty() {
+            let arguments: Vec<String> = self
+                .arguments
+                .iter()
+                .map(AttributeArgument::to_string)
+                .collect();
+            write!(f, "({})", arguments.join(", "))?;
+        }
+        Ok(())
+    }
+}
+impl Parse for Attribute {
+    fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+        ensure_tokens!(tokens, "@");
+        let name: Identifier = Identifier::parse(tokens)?;
+        let arguments: Vec<AttributeArgument> = if tokens.last().is_some_and(|token| token == "(") {
+            parse_delimited_list(tokens, "(", ",", ")", true)?
+        } else {
+            vec![]
+        };
+        Ok(Attribute { name, arguments })
+    }
+}
+
+/// Parse an integer literal, with an optional sign and radix prefix.
+/// With the `strtoint` feature (enabled by default), this uses `strtoint`,
+/// which also allows `_` digit separators.
+#[cfg(feature = "strtoint")]
+fn parse_integer<T: strtoint::StrToInt>(token: &str) -> Option<T> {
+    strtoint::strtoint(token).ok()
+}
+
+/// Integer types with `from_str_radix`, for [`parse_integer`] without the `strtoint` feature.
+#[cfg(not(feature = "strtoint"))]
+trait FromStrRadix: Sized {
+    fn from_str_radix(digits: &str, radix: u32) -> Option<Self>;
+}
+#[cfg(not(feature = "strtoint"))]
+macro_rules! impl_from_str_radix {
+    ($($ty:ty),+) => {
+        $(
+            impl FromStrRadix for $ty {
+                fn from_str_radix(digits: &str, radix: u32) -> Option<Self> {
+                    <$ty>::from_str_radix(digits, radix).ok()
+                }
+            }
+        )+
+    };
+}
+#[cfg(not(feature = "strtoint"))]
+impl_from_str_radix!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Parse an integer literal, with an optional sign and radix prefix.
+/// Without the `strtoint` feature, this uses `from_str_radix`,
+/// which does not allow `_` digit separators.
+#[cfg(not(feature = "strtoint"))]
+fn parse_integer<T: FromStrRadix>(token: &str) -> Option<T> {
+    let (sign, unsigned): (&str, &str) = match token.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", token.strip_prefix('+').unwrap_or(token)),
+    };
+    let (radix, digits): (u32, &str) = match unsigned.get(..2) {
+        Some("0x") => (16, &unsigned[2..]),
+        Some("0o") => (8, &unsigned[2..]),
+        Some("0b") => (2, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    T::from_str_radix(&format!("{sign}{digits}"), radix)
+}
+
+/// Check that an integer literal with a radix prefix (`0x`, `0o`, or `0b`) only has valid digits.
+fn check_radix_digits(token: &str) -> Result<(), ParseError> {
+    let unsigned: &str = token.strip_prefix(['+', '-']).unwrap_or(token);
+    let (radix, name): (u32, &str) = match unsigned.get(..2) {
+        Some("0x") => (16, "hex"),
+        Some("0o") => (8, "octal"),
+        Some("0b") => (2, "binary"),
+        _ => return Ok(()),
+    };
+
+    let digits: &str = &unsigned[2..];
+    ensure!(
+        digits.chars().any(|c| c != '_'),
+        ParseError::invalid(
+            token,
+            format!("Missing digits after `{}` in literal", &unsigned[..2])
+        )
+    );
+    if let Some(digit) = digits.chars().find(|&c| c != '_' && !c.is_digit(radix)) {
+        return Err(ParseError::invalid(
+            token,
+            format!("Invalid {name} digit `{digit}` in literal"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_parse, test_parse_exact, tokenize, tokens};
+
+    use super::*;
+
+    #[test]
+    fn test_ensure_tokens() {
+        fn parse_assignment(tokens: &mut Vec<String>) -> Result<(), ParseError> {
+            Identifier::parse(tokens)?;
+            ensure_tokens!(tokens, "=");
+            Literal::parse(tokens)?;
+            Ok(())
+        }
+
+        let mut tokens: Vec<String> = tokenize!("x = 5");
+        assert_eq!(parse_assignment(&mut tokens), Ok(()));
+
+        let mut tokens: Vec<String> = tokenize!("x == 5");
+        assert_eq!(
+            parse_assignment(&mut tokens),
+            Err(ParseError::InvalidToken {
+                token: "==".to_string(),
+                error: "Expected = (found `==`, did you mean `=`?)".to_string(),
+                expected: Some("=".to_string())
+            })
+        );
+
+        let mut tokens: Vec<String> = tokenize!("x + 5");
+        assert_eq!(
+            parse_assignment(&mut tokens),
+            Err(ParseError::InvalidToken {
+                token: "+".to_string(),
+                error: "Expected =".to_string(),
+                expected: Some("=".to_string())
+            })
+        );
+
+        let mut tokens: Vec<String> = tokenize!("x + 5");
+        let error: ParseError = parse_assignment(&mut tokens).unwrap_err();
+        assert_eq!(error.expected_token(), Some("="));
+        assert_eq!(
+            error.with_context("In assignment").expected_token(),
+            Some("=")
+        );
+        assert_eq!(
+            ParseError::invalid("+", "Expected =").expected_token(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_sequence() -> Result<(), ParseError> {
+        let mut tokens: Vec<String> = tokenize!("give @p stone 5 ;");
+        let (target, item, count) = parse_sequence!(
+            tokens,
+            "give",
+            target: crate::minecraft::Selector,
+            item: Identifier,
+            count: u32,
+            ";",
+        )?;
+        assert_eq!(target.to_string(), "@p");
+        assert_eq!(item, Identifier("stone".to_string()));
+        assert_eq!(count, 5);
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("(x)");
+        let (name,) = parse_sequence!(tokens, "(", name: Identifier, ")")?;
+        assert_eq!(name, Identifier("x".to_string()));
+
+        let mut tokens: Vec<String> = tokenize!("a b");
+        let () = parse_sequence!(tokens, "a", "b")?;
+
+        fn parse_let(tokens: &mut Vec<String>) -> Result<(Identifier, Literal), ParseError> {
+            parse_sequence!(tokens, "let", name: Identifier, "=", value: Literal)
+        }
+        let mut tokens: Vec<String> = tokenize!("let x 5");
+        assert_eq!(parse_let(&mut tokens), Err(ParseError::expected("5", "=")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_impl_grammar() {
+        #[derive(Debug, PartialEq)]
+        struct Assignment {
+            name: Identifier,
+            value: Literal,
+        }
+        impl_grammar!(Assignment { "let" name "=" value ";" });
+
+        let mut tokens: Vec<String> = tokenize!("let x = \"string\";");
+        let assignment: Assignment = Assignment::parse(&mut tokens).unwrap();
+        assert!(tokens.is_empty());
+        assert_eq!(
+            assignment,
+            Assignment {
+                name: Identifier("x".to_string()),
+                value: Literal::String("string".to_string())
+            }
+        );
+
+        let displayed: String = assignment.to_string();
+        assert_eq!(displayed, "let x = \"string\" ;");
+        let mut tokens: Vec<String> = tokenize!(&displayed);
+        test_parse!(tokens, Assignment, Ok(assignment));
+
+        let mut tokens: Vec<String> = tokenize!("let x 5;");
+        assert!(Assignment::parse(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_grammar() {
+        grammar!(Point = "(" x:i64 "," y:i64 ")");
+        grammar!(
+            /// Flags given to a name.
+            Flagged = name:Identifier "=" flags:(Flags<Identifier>)
+        );
+        let mut tokens: Vec<String> = tokenize!("(1, -2)");
+        test_parse!(tokens, Point, Ok(Point { x: 1, y: -2 }));
+        assert_eq!(Point { x: 1, y: -2 }.to_string(), "( 1 , -2 )");
+
+        let mut tokens: Vec<String> = tokenize!("(1 2)");
+        assert!(Point::parse(&mut tokens).is_err());
+
+        let mut tokens: Vec<String> = tokenize!("x = A | B");
+        test_parse!(
+            tokens,
+            Flagged,
+            Ok(Flagged {
+                name: Identifier("x".to_string()),
+                flags: Flags(vec![
+                    Identifier("A".to_string()),
+                    Identifier("B".to_string())
+                ])
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_nonzero() {
+        test_parse_exact!("1", NonZeroUsize, Ok(NonZeroUsize::new(1).unwrap()));
+        test_parse_exact!("-5", NonZeroI32, Ok(NonZeroI32::new(-5).unwrap()));
+
+        let mut tokens: Vec<String> = tokenize!("0 0x0 -0 -1");
+        test_parse!(
+            tokens,
+            NonZeroUsize,
+            Err(ParseError::invalid("0", "Integer must be nonzero"))
+        );
+        test_parse!(
+            tokens,
+            NonZeroU32,
+            Err(ParseError::invalid("0x0", "Integer must be nonzero"))
+        );
+        test_parse!(
+            tokens,
+            NonZeroI64,
+            Err(ParseError::invalid("0", "Integer must be nonzero"))
+        );
+        test_parse!(
+            tokens,
+            NonZeroU8,
+            Err(ParseError::invalid("1", "Integer out of range for u8"))
+        );
+
+        let error: ParseError = NonZeroU32::parse_str("0x0").unwrap_err();
+        assert_eq!(
+            error.span().map(|span| (span.start, span.end)),
+            Some((0, 3))
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let mut tokens: Vec<String> = tokenize!("500ms 2s 100ns 3us 1.5m .25s 0s");
+        for expected in [
+            Duration::from_millis(500),
+            Duration::from_secs(2),
+            Duration::from_nanos(100),
+            Duration::from_micros(3),
+            Duration::from_secs(90),
+            Duration::from_millis(250),
+            Duration::ZERO,
+        ] {
+            test_parse!(tokens, Duration, Ok(expected));
+        }
+        test_parse_exact!(
+            "18446744073709551615m",
+            Duration,
+            Err(ParseError::invalid(
+                "18446744073709551615m",
+                "Duration out of range"
+            ))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("5 5h ms 1..2s");
+        test_parse!(
+            tokens,
+            Duration,
+            Err(ParseError::invalid(
+                "5",
+                "Missing time unit, expected one of ns, us, ms, s, m"
+            ))
+        );
+        test_parse!(
+            tokens,
+            Duration,
+            Err(ParseError::invalid(
+                "5h",
+                "Unknown time unit `h`, expected one of ns, us, ms, s, m"
+            ))
+        );
+        test_parse!(
+            tokens,
+            Duration,
+            Err(ParseError::invalid("ms", "Invalid duration"))
+        );
+        test_parse!(
+            tokens,
+            Duration,
+            Err(ParseError::invalid("1..2s", "Invalid duration"))
+        );
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        let mut tokens: Vec<String> = tokenize!("255 256 -1 0x10 1.5 \"a\" 2");
+        test_parse!(tokens, u8, Ok(255));
+        test_parse!(
+            tokens,
+            u8,
+            Err(ParseError::invalid("256", "Integer out of range for u8"))
+        );
+        test_parse!(
+            tokens,
+            u32,
+            Err(ParseError::invalid("1", "Integer out of range for u32"))
+        );
+        test_parse!(tokens, i64, Ok(16));
+        test_parse!(
+            tokens,
+            i32,
+            Err(ParseError::invalid("1.5", "Expected integer"))
+        );
+        test_parse!(
+            tokens,
+            f64,
+            Err(ParseError::invalid("\"a\"", "Expected number"))
+        );
+        test_parse_exact!("2", f32, Ok(2.0));
+
+        let mut tokens: Vec<String> = tokenize!("1e300");
+        test_parse!(
+            tokens,
+            f32,
+            Err(ParseError::invalid("1e300", "Number out of range for f32"))
+        );
+
+        // Types wider than `isize` have their whole range.
+        test_parse_exact!("18446744073709551615", u64, Ok(u64::MAX));
+        test_parse_exact!("0xFFFFFFFFFFFFFFFF", u64, Ok(u64::MAX));
+        test_parse_exact!(
+            "-170141183460469231731687303715884105728",
+            i128,
+            Ok(i128::MIN)
+        );
+        test_parse_exact!("-0", u8, Ok(0));
+        test_parse_exact!("+7", i8, Ok(7));
+
+        let error: ParseError = u64::parse_str("18446744073709551616").unwrap_err();
+        assert!(error.eq_ignoring_span(&ParseError::invalid(
+            "18446744073709551616",
+            "Integer out of range for u64"
+        )));
+        assert_eq!(
+            error.span().map(|span| (span.start, span.end)),
+            Some((0, 20))
+        );
+        let error: ParseError = u8::parse_str("a -300").unwrap_err();
+        assert!(error.eq_ignoring_span(&ParseError::invalid("a", "Expected integer")));
+        assert!(u8::parse_str("-300")
+            .unwrap_err()
+            .eq_ignoring_span(&ParseError::invalid("300", "Integer out of range for u8")));
+        assert!(u8::parse_str("0x1G")
+            .unwrap_err()
+            .eq_ignoring_span(&ParseError::invalid(
+                "0x1G",
+                "Invalid hex digit `G` in literal"
+            )));
+    }
+
+    #[test]
+    fn test_parse_nested() {
+        #[derive(Debug, PartialEq)]
+        enum Nested {
+            Group(Box<Nested>),
+            Leaf(Identifier),
+        }
+        impl Parse for Nested {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                if tokens.last().is_some_and(|token| token == "(") {
+                    tokens.pop();
+                    let inner: Nested = parse_nested(tokens, Nested::parse)?;
+                    ensure_tokens!(tokens, ")");
+                    Ok(Nested::Group(Box::new(inner)))
+                } else {
+                    Ok(Nested::Leaf(Identifier::parse(tokens)?))
+                }
+            }
+        }
+
+        let mut tokens: Vec<String> = tokenize!("((x))");
+        test_parse!(
+            tokens,
+            Nested,
+            Ok(Nested::Group(Box::new(Nested::Group(Box::new(
+                Nested::Leaf(Identifier("x".to_string()))
+            )))))
+        );
+
+        set_recursion_limit(8);
+        let input: String = "(".repeat(9) + "x" + &")".repeat(9);
+        let mut tokens: Vec<String> = tokenize!(&input);
+        test_parse!(tokens, Nested, Err(ParseError::RecursionLimit));
+
+        let input: String = "(".repeat(8) + "x" + &")".repeat(8);
+        let mut tokens: Vec<String> = tokenize!(&input);
+        assert!(Nested::parse(&mut tokens).is_ok());
+        set_recursion_limit(DEFAULT_RECURSION_LIMIT);
+    }
+
+    #[test]
+    fn test_peek_n() {
+        let tokens: Vec<String> = tokenize!("a b c");
+
+        assert_eq!(peek_n(&tokens, 0), Some(&"a".to_string()));
+        assert_eq!(peek_n(&tokens, 1), Some(&"b".to_string()));
+        assert_eq!(peek_n(&tokens, 2), Some(&"c".to_string()));
+        assert_eq!(peek_n(&tokens, 3), None);
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn test_peek_kind() {
+        let mut tokens: Vec<String> = tokenize!(r#"x "s" 5 .5 -( ]; ~1 @"#);
+        let mut kinds: Vec<TokenKind> = vec![];
+        while let Some(kind) = peek_kind(&tokens) {
+            kinds.push(kind);
+            tokens.pop();
+        }
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Word,
+                TokenKind::String,
+                TokenKind::Number,
+                TokenKind::Number,
+                TokenKind::Special,
+                TokenKind::Special,
+                TokenKind::Special,
+                TokenKind::Special,
+                TokenKind::Special,
+                TokenKind::Special,
+            ]
+        );
+        assert_eq!(peek_kind(&tokens), None);
+    }
+
+    #[test]
+    fn test_keyword_type() {
+        keyword_type!(Struct, "struct");
+
+        let mut tokens: Vec<String> = tokenize!("struct enum");
+        test_parse!(tokens, Struct, Ok(Struct));
+        test_parse!(
+            tokens,
+            Struct,
+            Err(ParseError::InvalidToken {
+                token: "enum".to_string(),
+                error: "Expected struct".to_string(),
+                expected: Some("struct".to_string())
+            })
+        );
+        assert_eq!(Struct.to_string(), "struct");
+    }
+
+    #[test]
+    fn test_string_enum() {
+        string_enum!(Direction {
+            North = "north",
+            South = "south",
+            East = "east",
+            West = "west",
+        });
+
+        test_parse_exact!("north", Direction, Ok(Direction::North));
+        test_parse_exact!("\"west\"", Direction, Ok(Direction::West));
+        assert_eq!(Direction::East.to_string(), "east");
+        assert_eq!(Direction::ALL.len(), 4);
+
+        let mut tokens: Vec<String> = tokenize!("up \"North\"");
+        let expected: Vec<String> = ["north", "south", "east", "west"]
+            .map(String::from)
+            .to_vec();
+        test_parse!(
+            tokens,
+            Direction,
+            Err(ParseError::ExpectedOneOf {
+                token: "up".to_string(),
+                expected: expected.clone()
+            })
+        );
+        assert_eq!(tokens, tokens!["up", "\"North\""]);
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Direction,
+            Err(ParseError::ExpectedOneOf {
+                token: "\"North\"".to_string(),
+                expected
+            })
+        );
+    }
+
+    #[test]
+    fn test_optional_keyword() {
+        let mut tokens: Vec<String> = tokenize!("mut x");
+
+        assert!(parse_optional_keyword(&mut tokens, "mut"));
+        assert!(!parse_optional_keyword(&mut tokens, "mut"));
+        assert_eq!(tokens, vec!["x".to_string()]);
+
+        tokens.clear();
+        assert!(!parse_optional_keyword(&mut tokens, "mut"));
+    }
+
+    #[test]
+    fn test_parse_if_keyword() {
+        let parse_else = |tokens: &mut Vec<String>| {
+            parse_if_keyword(tokens, "else", |tokens| {
+                ensure_tokens!(tokens, "{");
+                let body: Identifier = Identifier::parse(tokens)?;
+                ensure_tokens!(tokens, "}");
+                Ok(body)
+            })
+        };
+
+        let mut tokens: Vec<String> = tokenize!("else { a } b");
+        assert_eq!(
+            parse_else(&mut tokens),
+            Ok(Some(Identifier("a".to_string())))
+        );
+        assert_eq!(tokens, tokens!["b"]);
+
+        let mut tokens: Vec<String> = tokenize!("b");
+        assert_eq!(parse_else(&mut tokens), Ok(None));
+        assert_eq!(tokens, tokens!["b"]);
+
+        let mut tokens: Vec<String> = tokenize!("else a");
+        assert_eq!(parse_else(&mut tokens), Err(ParseError::expected("a", "{")));
+    }
+
+    #[test]
+    fn test_parse_separated() -> Result<(), ParseError> {
+        let identifiers = |names: &[&str]| -> Vec<Identifier> {
+            names
+                .iter()
+                .map(|name| Identifier(name.to_string()))
+                .collect()
+        };
+
+        let mut tokens: Vec<String> = tokenize!("a, b,c ]");
+        assert_eq!(
+            parse_separated::<Identifier>(&mut tokens, ",")?,
+            identifiers(&["a", "b", "c"])
+        );
+        assert_eq!(tokens, vec!["]".to_string()]);
+
+        let mut tokens: Vec<String> = tokenize!("a b");
+        assert_eq!(
+            parse_separated::<Identifier>(&mut tokens, ",")?,
+            identifiers(&["a"])
+        );
+        assert_eq!(tokens, vec!["b".to_string()]);
+
+        let mut tokens: Vec<String> = tokens!["5"];
+        assert_eq!(
+            parse_separated::<Identifier>(&mut tokens, ",")?,
+            Vec::<Identifier>::new()
+        );
+        assert_eq!(tokens.len(), 1);
+
+        let mut tokens: Vec<String> = tokenize!("a, b,");
+        assert_eq!(
+            parse_separated::<Identifier>(&mut tokens, ",")?,
+            identifiers(&["a", "b"])
+        );
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("a = 1, b = ; c = 3");
+        assert_eq!(
+            parse_separated::<KeyValue<Identifier, Literal>>(&mut tokens, ","),
+            Err(Literal::parse(&mut tokens![";"]).unwrap_err())
+        );
+        assert_eq!(tokens, tokens![";", "c", "=", "3"]);
+
+        let mut tokens: Vec<String> = Tokenizer::new("[\n  a, b\n  c,\n\n  d\n]")
+            .newlines(true)
+            .map(|token| token.text)
+            .collect();
+        tokens.reverse();
+        ensure_tokens!(tokens, "[");
+        assert_eq!(
+            parse_separated_any::<Identifier>(&mut tokens, &[",", "\n"])?,
+            identifiers(&["a", "b", "c", "d"])
+        );
+        assert_eq!(tokens, vec!["]".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_separated_nonempty() -> Result<(), ParseError> {
+        let mut tokens: Vec<String> = tokenize!("a, b)");
+        assert_eq!(
+            parse_separated_nonempty::<Identifier>(&mut tokens, ",", "parameter")?,
+            vec![Identifier("a".to_string()), Identifier("b".to_string())]
+        );
+        assert_eq!(tokens, tokens![")"]);
+
+        let mut tokens: Vec<String> = tokenize!(")");
+        assert_eq!(
+            parse_separated_nonempty::<Identifier>(&mut tokens, ",", "parameter"),
+            Err(Identifier::parse(&mut tokens![")"])
+                .unwrap_err()
+                .with_context("Expected at least one parameter"))
+        );
+        assert_eq!(tokens, tokens![")"]);
+
+        let mut tokens: Vec<String> = tokenize!(", , a, b");
+        assert_eq!(
+            parse_separated_nonempty::<Identifier>(&mut tokens, ",", "parameter"),
+            Err(Identifier::parse(&mut tokens![","])
+                .unwrap_err()
+                .with_context("Expected at least one parameter"))
+        );
+        assert_eq!(tokens, tokens![",", ",", "a", ",", "b"]);
+
+        let mut tokens: Vec<String> = tokenize!("");
+        let error: ParseError =
+            parse_separated_nonempty::<KeyValue<Identifier, Literal>>(&mut tokens, ",", "property")
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected at least one property: Unexpected EOF, expected identifier"
+        );
+
+        let mut tokens: Vec<String> = tokenize!("a = ;");
+        assert_eq!(
+            parse_separated_nonempty::<KeyValue<Identifier, Literal>>(&mut tokens, ",", "property"),
+            Err(Literal::parse(&mut tokens![";"])
+                .unwrap_err()
+                .with_context("Expected at least one property"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_while() {
+        let mut tokens: Vec<String> = tokenize!("a b c } d");
+        let items: Vec<Identifier> = parse_while(&mut tokens, |token| token != "}").unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(tokens.last(), Some(&"}".to_string()));
+
+        let mut tokens: Vec<String> = tokenize!("a b c");
+        let items: Vec<Identifier> = parse_while(&mut tokens, |token| token != "}").unwrap();
+        assert_eq!(items.len(), 3);
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("a 5 }");
+        assert_eq!(
+            parse_while::<Identifier>(&mut tokens, |token| token != "}"),
+            Err(ParseError::invalid(
+                "5",
+                "Identifiers must not start with a number and can only contain letters, numbers, and underscores"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_iter() {
+        let mut tokens: Vec<String> = tokenize!("a b c");
+        let items: Vec<Result<Identifier, ParseError>> = parse_iter(&mut tokens).collect();
+        assert_eq!(items.len(), 3);
+        assert!(items.iter().all(Result::is_ok));
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("");
+        assert_eq!(parse_iter::<Identifier>(&mut tokens).count(), 0);
+
+        let mut tokens: Vec<String> = tokenize!("a 5 b");
+        let mut items = parse_iter::<Identifier>(&mut tokens);
+        assert_eq!(items.next(), Some(Ok(Identifier("a".to_string()))));
+        assert!(items.next().is_some_and(|item| item.is_err()));
+        assert_eq!(items.next(), None);
+        drop(items);
+        assert_eq!(tokens, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_delimited_list() {
+        let parse = |input: &str, allow_trailing: bool| {
+            let mut tokens: Vec<String> = tokenize!(input);
+            parse_delimited_list::<Literal>(&mut tokens, "[", ",", "]", allow_trailing)
+                .map(|items| (items, tokens.len()))
+        };
+
+        assert_eq!(parse("[] x", false), Ok((vec![], 1)));
+        assert_eq!(parse("[1]", false), Ok((vec![Literal::Integer(1)], 0)));
+        assert_eq!(
+            parse("[1, 2, 3]", false),
+            Ok((
+                vec![
+                    Literal::Integer(1),
+                    Literal::Integer(2),
+                    Literal::Integer(3)
+                ],
+                0
+            ))
+        );
+        assert_eq!(
+            parse("[1, 2,]", true),
+            Ok((vec![Literal::Integer(1), Literal::Integer(2)], 0))
+        );
+        assert_eq!(
+            parse("[1, 2,]", false),
+            Err(ParseError::invalid(
+                "]",
+                "Trailing , is not allowed before ]"
+            ))
+        );
+        assert_eq!(
+            parse("[1 2]", false),
+            Err(ParseError::expected("2", ", or ]"))
+        );
+        assert_eq!(
+            parse("[1, 2", false),
+            Err(ParseError::end_of_file(", or ]"))
+        );
+        assert_eq!(parse("[1,", true), Err(ParseError::end_of_file("]")));
+        assert_eq!(parse("(1)", false), Err(ParseError::expected("(", "[")));
+        assert_eq!(
+            parse("[,]", true).map(|_| ()),
+            Err(ParseError::invalid(",", "Invalid literal"))
+        );
+    }
+
+    #[test]
+    fn test_parse_terminated() -> Result<(), ParseError> {
+        let mut tokens: Vec<String> = tokenize!("{ a; b; c } d");
+        ensure_tokens!(tokens, "{");
+        let statements: Vec<Identifier> = parse_terminated(&mut tokens, "}", ";")?;
+        assert_eq!(statements.len(), 3);
+        ensure_tokens!(tokens, "}");
+        assert_eq!(tokens, vec!["d".to_string()]);
+
+        let mut tokens: Vec<String> = tokenize!("a; b; }");
+        assert_eq!(
+            parse_terminated::<Identifier>(&mut tokens, "}", ";")?.len(),
+            2
+        );
+        let mut tokens: Vec<String> = tokenize!("}");
+        assert_eq!(
+            parse_terminated::<Identifier>(&mut tokens, "}", ";")?.len(),
+            0
+        );
+        assert_eq!(tokens.len(), 1);
+
+        let mut tokens: Vec<String> = tokenize!("a b }");
+        assert_eq!(
+            parse_terminated::<Identifier>(&mut tokens, "}", ";"),
+            Err(ParseError::expected("b", "; or }"))
+        );
+        let mut tokens: Vec<String> = tokenize!("a; b");
+        assert_eq!(
+            parse_terminated::<Identifier>(&mut tokens, "}", ";"),
+            Err(ParseError::end_of_file("; or }"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_until() -> Result<(), ParseError> {
+        fn items(tokens: &mut Vec<String>, end: ListEnd) -> Result<Vec<Identifier>, ParseError> {
+            parse_until(tokens, end, ";")
+        }
+
+        let mut tokens: Vec<String> = tokenize!("a; b; } c; d;");
+        assert_eq!(items(&mut tokens, ListEnd::Token("}"))?.len(), 2);
+        ensure_tokens!(tokens, "}");
+        assert_eq!(items(&mut tokens, ListEnd::EndOfFile)?.len(), 2);
+        assert!(tokens.is_empty());
+        assert_eq!(items(&mut tokens, ListEnd::EndOfFile)?.len(), 0);
+
+        let mut tokens: Vec<String> = tokenize!("a; b");
+        assert_eq!(items(&mut tokens, ListEnd::EndOfFile)?.len(), 2);
+        let mut tokens: Vec<String> = tokenize!("a; b");
+        assert_eq!(
+            items(&mut tokens, ListEnd::Token("}")),
+            Err(ParseError::end_of_file("; or }"))
+        );
+        assert_eq!(tokens.len(), 0);
+
+        let mut tokens: Vec<String> = tokenize!("a; b }");
+        assert_eq!(
+            items(&mut tokens, ListEnd::EndOfFile),
+            Err(ParseError::expected("}", "; or end of file"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_binary_left() {
+        fn difference(tokens: &mut Vec<String>) -> Result<String, ParseError> {
+            parse_binary_left(
+                tokens,
+                |tokens| Identifier::parse(tokens).map(String::from),
+                &["-", "+"],
+                |left, operator, right| format!("({left} {operator} {right})"),
+            )
+        }
+
+        let mut tokens: Vec<String> = tokenize!("a - b + c ; d");
+        assert_eq!(difference(&mut tokens), Ok("((a - b) + c)".to_string()));
+        assert_eq!(tokens, tokens![";", "d"]);
+
+        let mut tokens: Vec<String> = tokenize!("a");
+        assert_eq!(difference(&mut tokens), Ok("a".to_string()));
+
+        let mut tokens: Vec<String> = tokenize!("a -");
+        assert_eq!(
+            difference(&mut tokens),
+            Err(ParseError::end_of_file("identifier"))
+        );
+    }
+
+    #[test]
+    fn test_parse_statements() {
+        let mut tokens: Vec<String> = tokenize!("a; b;; c; }");
+        assert_eq!(
+            parse_statements::<Identifier>(&mut tokens),
+            Ok(vec![
+                Identifier("a".to_string()),
+                Identifier("b".to_string()),
+                Identifier("c".to_string())
+            ])
+        );
+        assert_eq!(tokens, vec!["}".to_string()]);
+
+        let mut tokens: Vec<String> = tokenize!("");
+        assert_eq!(parse_statements::<Identifier>(&mut tokens), Ok(vec![]));
+
+        let mut tokens: Vec<String> = tokenize!("a; b c;");
+        assert_eq!(
+            parse_statements::<Identifier>(&mut tokens),
+            Err(ParseError::expected("c", ";"))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("a; b");
+        assert_eq!(
+            parse_statements::<Identifier>(&mut tokens),
+            Err(ParseError::end_of_file(";"))
+        );
+    }
+
+    #[test]
+    fn test_recovered() -> Result<(), ParseError> {
+        let mut tokens: Vec<String> = tokenize!("first; 5 + 3; last");
+
+        test_parse!(
+            tokens,
+            Recovered<Identifier>,
+            Ok(Recovered::Parsed(Identifier("first".to_string())))
+        );
+        ensure_tokens!(tokens, ";");
+        assert!(tokens.ends_with(&["5".to_string()]));
+        assert!(matches!(
+            Recovered::<Identifier>::parse(&mut tokens),
+            Ok(Recovered::Error(ParseError::InvalidToken { .. }))
+        ));
+        ensure_tokens!(tokens, ";");
+        test_parse!(
+            tokens,
+            Recovered<Identifier>,
+            Ok(Recovered::Parsed(Identifier("last".to_string())))
+        );
+
+        assert!(tokens.is_empty());
+        test_parse!(
+            tokens,
+            Recovered<Identifier>,
+            Ok(Recovered::Error(ParseError::end_of_file("identifier")))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_to_sync() {
+        let mut tokens: Vec<String> = tokenize!("a b ) } c ; d");
+        assert_eq!(skip_to_sync(&mut tokens, &[";", "}"]), 3);
+        assert_eq!(tokens.last().map(String::as_str), Some("}"));
+        assert_eq!(skip_to_sync(&mut tokens, &["}"]), 0);
+        tokens.pop();
+        assert_eq!(skip_to_sync(&mut tokens, &[";"]), 1);
+        assert_eq!(tokens, tokens![";", "d"]);
+        assert_eq!(skip_to_sync(&mut tokens, &["}"]), 2);
+        assert!(tokens.is_empty());
+    }
+
+    #[test]
+    fn test_parse_str() {
+        assert_eq!(
+            Identifier::parse_str("cool_identifier"),
+            Ok(Identifier("cool_identifier".to_string()))
+        );
+        assert_eq!(Literal::parse_str("-5 trailing"), Ok(Literal::Integer(-5)));
+        assert!(Identifier::parse_str("")
+            .unwrap_err()
+            .eq_ignoring_span(&ParseError::end_of_file("identifier")));
+    }
+
+    #[test]
+    fn test_atomic() {
+        let mut tokens: Vec<String> = tokenize!("a.b.; x");
+        let snapshot: Vec<String> = tokens.clone();
+
+        assert!(Path::parse(&mut tokens.clone()).is_err());
+        assert!(Atomic::<Path>::parse(&mut tokens).is_err());
+        assert_eq!(tokens, snapshot);
+
+        let mut tokens: Vec<String> = tokenize!("\"string\" x");
+        assert!(Atomic::<Identifier>::parse(&mut tokens).is_err());
+        test_parse!(
+            tokens,
+            Atomic<Literal>,
+            Ok(Atomic(Literal::String("string".to_string())))
+        );
+        test_parse!(
+            tokens,
+            Atomic<Identifier>,
+            Ok(Atomic(Identifier("x".to_string())))
+        );
+
+        // Longer than 32 tokens, with an optional value that is only known to end at `x`.
+        #[derive(Debug, PartialEq)]
+        struct Pair(Option<Path>, Identifier);
+        impl Parse for Pair {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                Ok(Pair(Option::parse(tokens)?, Identifier::parse(tokens)?))
+            }
+        }
+        let path: String = vec!["a"; 40].join(".");
+        let mut tokens: Vec<String> = tokenize!(&format!("{path} x"));
+        let Atomic(Pair(path, x)) = Atomic::<Pair>::parse(&mut tokens).unwrap();
+        assert_eq!(path.map(|path| path.0.len()), Some(40));
+        assert_eq!(x, Identifier("x".to_string()));
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!(&format!("{} ;", vec!["a"; 40].join(".")));
+        let snapshot: Vec<String> = tokens.clone();
+        assert!(Atomic::<Pair>::parse(&mut tokens).is_err());
+        assert_eq!(tokens, snapshot);
+        // A statement that finds its end by looking ahead itself, rather than with `peek_n`.
+        #[derive(Debug, PartialEq)]
+        struct Statement(usize);
+        impl Parse for Statement {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                let len: usize = tokens
+                    .iter()
+                    .rev()
+                    .position(|token| token == ";")
+                    .ok_or_else(|| ParseError::end_of_file(";"))?;
+                tokens.truncate(tokens.len() - len - 1);
+                Ok(Statement(len))
+            }
+        }
+        let source: String = format!("{}; x", vec!["a"; 40].join(" "));
+        let mut tokens: Vec<String> = tokenize!(&source);
+        assert_eq!(Statement::parse(&mut tokens), Ok(Statement(40)));
+        let mut tokens: Vec<String> = tokenize!(&source);
+        test_parse!(tokens, Atomic<Statement>, Ok(Atomic(Statement(40))));
+        let mut tokens: Vec<String> = tokenize!(&source);
+        test_parse!(tokens, Option<Statement>, Ok(Some(Statement(40))));
+        let mut tokens: Vec<String> = tokenize!(&source);
+        assert_eq!(parse_committed(&mut tokens), Ok(Some(Statement(40))));
+        assert_eq!(tokens, vec!["x".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_enum() {
+        #[derive(Debug, PartialEq)]
+        enum Declaration {
+            Struct(Identifier),
+            Const(Identifier, Literal),
+        }
+        impl ParseEnum for Declaration {
+            fn variants() -> &'static [(&'static str, ParseFn<Self>)] {
+                &[
+                    ("struct", |tokens| {
+                        Ok(Declaration::Struct(Identifier::parse(tokens)?))
+                    }),
+                    ("const", |tokens| {
+                        let name: Identifier = Identifier::parse(tokens)?;
+                        ensure_tokens!(tokens, "=");
+                        Ok(Declaration::Const(name, Literal::parse(tokens)?))
+                    }),
+                ]
+            }
+        }
+        impl Parse for Declaration {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                Declaration::parse_enum(tokens)
+            }
+        }
+
+        let mut tokens: Vec<String> = tokenize!("struct A const B = 5 enum C");
+        test_parse!(
+            tokens,
+            Declaration,
+            Ok(Declaration::Struct(Identifier("A".to_string())))
+        );
+        test_parse!(
+            tokens,
+            Declaration,
+            Ok(Declaration::Const(
+                Identifier("B".to_string()),
+                Literal::Integer(5)
+            ))
+        );
+
+        let error: ParseError = Declaration::parse(&mut tokens).unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::ExpectedOneOf {
+                token: "enum".to_string(),
+                expected: vec!["struct".to_string(), "const".to_string()]
+            }
+        );
+        assert_eq!(error.to_string(), "Expected one of struct, const: enum");
+        assert_eq!(tokens.len(), 2);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let mut tokens: Vec<String> =
+            tokenize!("127.0.0.1 ::1 192.168.0.1:25565 fe80::1 300.0.0.1");
+
+        test_parse!(tokens, Ipv4Addr, Ok(Ipv4Addr::LOCALHOST));
+        test_parse!(tokens, Ipv6Addr, Ok(Ipv6Addr::LOCALHOST));
+        test_parse!(
+            tokens,
+            SocketAddr,
+            Ok(SocketAddr::from(([192, 168, 0, 1], 25565)))
+        );
+        test_parse!(tokens, IpAddr, Ok("fe80::1".parse().unwrap()));
+        test_parse!(
+            tokens,
+            Ipv4Addr,
+            Err(ParseError::invalid(
+                "300.0.0.1",
+                "invalid IPv4 address syntax"
+            ))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("42");
+        assert_eq!(parse_from_str::<u8>(&mut tokens), Ok(42));
+    }
+
+    #[test]
+    fn test_error_context() {
+        let parse_block = |tokens: &mut Vec<String>| -> Result<Vec<Identifier>, ParseError> {
+            ensure_tokens!(tokens, "{");
+            let statements: Vec<Identifier> =
+                parse_statements(tokens).map_err(|error| error.with_context("In statement"))?;
+            ensure_tokens!(tokens, "}");
+            Ok(statements)
+        };
+
+        let mut tokens: Vec<String> = tokenize!("{ a; 5; }");
+        let error: ParseError = parse_block(&mut tokens)
+            .map_err(|error| error.with_context("In block"))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "In block: In statement: Invalid token: Identifiers must not start with a number and can only contain letters, numbers, and underscores: 5"
+        );
+        assert_eq!(
+            error,
+            ParseError::invalid(
+                "5",
+                "Identifiers must not start with a number and can only contain letters, numbers, and underscores"
+            )
+            .with_context("In statement")
+            .with_context("In block")
+        );
+        assert_ne!(error, ParseError::eof().with_context("In block"));
+        assert!(std::error::Error::source(&error).is_some());
+
+        let span: Span = Span {
+            start: 5,
+            end: 6,
+            line: 1,
+            column: 6,
+        };
+        let error: ParseError = ParseError::eof().with_span(span).with_context("In block");
+        assert_eq!(error.span(), Some(span));
+        assert!(error.eq_ignoring_span(&ParseError::eof().with_context("In block")));
+
+        let mut tokens: Vec<String> = tokenize!("{ a;");
+        let error: ParseError = parse_block(&mut tokens)
+            .map_err(|error| error.with_context("In block"))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "In block: Unexpected EOF, expected }");
+        assert!(error.is_end_of_file());
+        assert_eq!(error, ParseError::end_of_file("}").with_context("In block"));
+        assert_eq!(ParseError::eof().to_string(), "Unexpected EOF");
+        assert_ne!(ParseError::end_of_file(")"), ParseError::eof());
+        assert_eq!(error.expected_token(), Some("}"));
+        assert_eq!(ParseError::eof().expected_token(), None);
+        assert!(!ParseError::RecursionLimit.is_end_of_file());
+    }
+
+    #[test]
+    fn test_error_traits() -> anyhow::Result<()> {
+        let boxed = || -> Result<Literal, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(Literal::parse_str("@")?)
+        };
+        assert_eq!(
+            boxed().unwrap_err().to_string(),
+            "Invalid token: Invalid literal: @ at line 1, column 1"
+        );
+
+        let error: anyhow::Error = Literal::parse_str("@")
+            .map_err(anyhow::Error::from)
+            .unwrap_err();
+        assert_eq!(
+            error
+                .downcast_ref::<ParseError>()
+                .and_then(ParseError::found_token),
+            Some("@")
+        );
+        assert_eq!(Literal::parse_str("1")?, Literal::Integer(1));
+        Ok(())
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_miette_diagnostic() {
+        use miette::{Diagnostic, LabeledSpan, Report};
+
+        let source: &str = "a 2";
+        let error: ParseError = KeyValue::<Identifier, Literal>::parse_str(source).unwrap_err();
+        let labels: Vec<LabeledSpan> = error.labels().unwrap().collect();
+        assert_eq!(
+            labels,
+            vec![LabeledSpan::new(Some("expected =".to_string()), 2, 1)]
+        );
+        assert!(ParseError::invalid("x", "Invalid").labels().is_none());
+
+        let report: Report = Report::new(error).with_source_code(source);
+        let snippet = report
+            .source_code()
+            .unwrap()
+            .read_span(labels[0].inner(), 0, 0)
+            .unwrap();
+        assert_eq!(snippet.data(), b"2");
+        assert_eq!(report.labels().map(Iterator::count), Some(1));
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let error: ParseError = Identifier::parse_str("\n  5").unwrap_err();
+        let span: Span = Span {
+            start: 3,
+            end: 4,
+            line: 2,
+            column: 3,
+        };
+        assert_eq!(error.span(), Some(span));
+        assert!(error.eq_ignoring_span(&ParseError::invalid(
+            "5",
+            "Identifiers must not start with a number and can only contain letters, numbers, and underscores"
+        )));
+        assert_ne!(&error, error.unspanned());
+        assert_eq!(error, error.unspanned().clone().with_span(span));
+        assert!(error
+            .clone()
+            .with_context("In block")
+            .eq_ignoring_span(&error.unspanned().clone().with_context("In block")));
+        assert_eq!(
+            ParseError::eof().with_span(span).to_string(),
+            "Unexpected EOF at line 2, column 3"
+        );
+
+        let error: ParseError = KeyValue::<Identifier, Literal>::parse_str("a b").unwrap_err();
+        assert_eq!(error.span().map(|span| span.start), Some(2));
+
+        let error: ParseError = KeyValue::<Identifier, Literal>::parse_str("a =  ").unwrap_err();
+        assert!(error.eq_ignoring_span(&ParseError::end_of_file("literal")));
+        assert_eq!(
+            error.span().map(|span| (span.start, span.column)),
+            Some((5, 6))
+        );
+    }
+
+    #[test]
+    fn test_option() {
+        impl_grammar!(Assignment { "let" name "=" value ";" });
+        #[derive(Debug, PartialEq)]
+        struct Assignment {
+            name: Identifier,
+            value: Literal,
+        }
+
+        let mut tokens: Vec<String> = tokenize!("let a = 1; x");
+        let assignment: Option<Assignment> = Option::parse(&mut tokens).unwrap();
+        assert_eq!(
+            assignment.map(|assignment| assignment.to_string()),
+            Some("let a = 1 ;".to_string())
+        );
+        test_parse!(tokens, Option<Assignment>, Ok(None));
+        assert_eq!(tokens, vec!["x".to_string()]);
+
+        let mut tokens: Vec<String> = tokenize!("let a 1;");
+        test_parse!(tokens, Option<Assignment>, Ok(None));
+        assert_eq!(tokens.len(), 4);
+
+        let mut tokens: Vec<String> = tokenize!("x");
+        assert!(parse_committed::<Assignment>(&mut tokens)
+            .unwrap()
+            .is_none());
+        assert_eq!(tokens, vec!["x".to_string()]);
+
+        let mut tokens: Vec<String> = tokenize!("let 5");
+        assert!(parse_committed::<Assignment>(&mut tokens).is_err());
+
+        let mut tokens: Vec<String> = tokenize!("let a 1;");
+        assert_eq!(
+            parse_committed::<Assignment>(&mut tokens).err(),
+            Some(ParseError::expected("1", "="))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("let a = 1;");
+        assert!(parse_committed::<Assignment>(&mut tokens)
+            .unwrap()
+            .is_some());
+        assert!(tokens.is_empty());
+
+        // Consuming a single token is enough to commit.
+        let mut tokens: Vec<String> = tokenize!("x 5");
+        assert_eq!(
+            parse_committed::<KeyValue<Identifier, Literal>>(&mut tokens),
+            Err(ParseError::expected("5", "="))
+        );
+    }
+
+    #[test]
+    fn test_parse_or_default() {
+        #[derive(Debug, Default, PartialEq)]
+        struct Count(isize);
+        impl Parse for Count {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                match Literal::parse(tokens)? {
+                    Literal::Integer(value) => Ok(Count(value)),
+                    literal => Err(ParseError::invalid(literal.to_string(), "Expected integer")),
+                }
+            }
+        }
+
+        let mut tokens: Vec<String> = tokenize!("5 \"five\"");
+        assert_eq!(parse_or_default::<Count>(&mut tokens), Count(5));
+        assert_eq!(parse_or_default::<Count>(&mut tokens), Count(0));
+        assert_eq!(tokens, vec!["\"five\"".to_string()]);
+
+        tokens.clear();
+        assert_eq!(parse_or_default::<Count>(&mut tokens), Count(0));
+    }
+
+    #[test]
+    fn test_unit() {
+        let mut tokens: Vec<String> = tokenize!("cool_identifier");
+
+        test_parse!(tokens, (), Ok(()));
+        assert_eq!(tokens, vec!["cool_identifier".to_string()]);
+
+        tokens.clear();
+        test_parse!(tokens, (), Ok(()));
+    }
+
+    #[test]
+    fn test_identifier() {
+        let mut tokens: Vec<String> = tokenize!("cool_identifier");
+
+        test_parse!(
+            tokens,
+            Identifier,
+            Ok(Identifier("cool_identifier".to_string()))
+        );
+
+        assert!(tokens.is_empty());
+        test_parse!(
+            tokens,
+            Identifier,
+            Err(ParseError::end_of_file("identifier"))
+        );
+    }
+
+    #[test]
+    fn test_quoted_identifier() {
+        let mut tokens: Vec<String> = tokenize!(r"`my name` `a\`b` `c\\` `plain`");
+
+        let identifier: Identifier = Identifier::parse(&mut tokens).unwrap();
+        assert_eq!(identifier, Identifier("my name".to_string()));
+        assert_eq!(identifier.to_string(), "`my name`");
+        let identifier: Identifier = Identifier::parse(&mut tokens).unwrap();
+        assert_eq!(identifier, Identifier("a`b".to_string()));
+        assert_eq!(identifier.to_string(), r"`a\`b`");
+        test_parse!(tokens, Identifier, Ok(Identifier(r"c\".to_string())));
+        let identifier: Identifier = Identifier::parse(&mut tokens).unwrap();
+        assert_eq!(identifier, Identifier("plain".to_string()));
+        assert_eq!(identifier.to_string(), "plain");
+        assert!(tokens.is_empty());
+
+        for (token, error) in [
+            ("``", "Empty identifier"),
+            (r"`a\n`", "Unknown escape `\\n` in quoted identifier"),
+            ("`open", "Unterminated quoted identifier"),
+            (r"`a\`", "Unterminated quoted identifier"),
+        ] {
+            let mut tokens: Vec<String> = tokenize!(token);
+            test_parse!(tokens, Identifier, Err(ParseError::invalid(token, error)));
+        }
+    }
+
+    #[test]
+    fn test_parse_interned() {
+        let mut interner: Interner = Interner::new();
+        let mut tokens: Vec<String> = tokenize!("a b a `b` 5");
+
+        let symbols: Vec<Symbol> = (0..4)
+            .map(|_| Identifier::parse_interned(&mut tokens, &mut interner).unwrap())
+            .collect();
+        assert_eq!(symbols[0], symbols[2]);
+        assert_eq!(symbols[1], symbols[3]);
+        assert_ne!(symbols[0], symbols[1]);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.resolve(symbols[1]), "b");
+        assert!(Identifier::parse_interned(&mut tokens, &mut interner).is_err());
+    }
+
+    #[test]
+    fn test_parse_canonical() {
+        let mut tokens: Vec<String> = tokenize!("MyName myname my_name");
+        let first: CanonicalIdentifier =
+            Identifier::parse_canonical(&mut tokens, CanonForm::Lowercase).unwrap();
+        assert_eq!(first.canonical(), "myname");
+        assert_eq!(first.original(), "MyName");
+        assert_eq!(first.to_string(), "MyName");
+        let second: CanonicalIdentifier =
+            Identifier::parse_canonical(&mut tokens, CanonForm::Lowercase).unwrap();
+        assert_eq!(first, second);
+        assert!(first == *"myname");
+        let third: CanonicalIdentifier =
+            Identifier::parse_canonical(&mut tokens, CanonForm::Lowercase).unwrap();
+        assert_ne!(first, third);
+
+        for (input, expected) in [
+            ("myName", "my_name"),
+            ("MyName", "my_name"),
+            ("my__name", "my_name"),
+            ("HTTPServer2Go", "httpserver2_go"),
+            ("_private__", "_private__"),
+            ("__", "__"),
+        ] {
+            assert_eq!(CanonForm::SnakeCase.apply(input), expected, "{input}");
+        }
+
+        let mut tokens: Vec<String> = tokenize!("5a");
+        assert!(Identifier::parse_canonical(&mut tokens, CanonForm::SnakeCase).is_err());
+    }
+
+    #[test]
+    fn test_identifier_str() {
+        let identifier: Identifier = Identifier("name".to_string());
+        assert_eq!(identifier.as_str(), "name");
+        assert_eq!(identifier.as_ref() as &str, "name");
+        assert!(identifier.starts_with("na"));
+        assert_eq!(identifier.len(), 4);
+        assert_eq!(String::from(identifier.clone()), "name");
+        assert!(identifier == "name");
+        assert!(identifier == *"name");
+        assert!(identifier != "other");
+        assert_eq!(identifier, Identifier("name".to_string()));
+        let string: String = identifier.into();
+        assert_eq!(string, "name");
+    }
+
+    #[test]
+    fn test_lenient_identifier() {
+        let lenient = |name: &str| Ok(LenientIdentifier(name.to_string()));
+        test_parse_exact!("1x", LenientIdentifier, lenient("1x"));
+        test_parse_exact!("2a_b", LenientIdentifier, lenient("2a_b"));
+        test_parse_exact!("12", LenientIdentifier, lenient("12"));
+        test_parse_exact!("name", LenientIdentifier, lenient("name"));
+        test_parse_exact!("`a b`", LenientIdentifier, lenient("a b"));
+
+        let mut tokens: Vec<String> = tokens!["1$"];
+        test_parse!(
+            tokens,
+            LenientIdentifier,
+            Err(ParseError::invalid(
+                "1$",
+                "Identifiers can only contain letters, numbers, and underscores"
+            ))
+        );
+        let mut tokens: Vec<String> = tokenize!("1x");
+        assert!(Identifier::parse(&mut tokens).is_err());
+
+        assert_eq!(LenientIdentifier("1x".to_string()).to_string(), "1x");
+        assert_eq!(LenientIdentifier("a b".to_string()).to_string(), "`a b`");
+    }
+
+    #[test]
+    fn test_path() {
+        test_parse_exact!(
+            "a.b.c",
+            Path,
+            Ok(Path(vec![
+                Identifier("a".to_string()),
+                Identifier("b".to_string()),
+                Identifier("c".to_string())
+            ]))
+        );
+        test_parse_exact!(
+            "single",
+            Path,
+            Ok(Path(vec![Identifier("single".to_string())]))
+        );
+
+        let mut tokens: Vec<String> = tokenize!("a.b.");
+        test_parse!(tokens, Path, Err(ParseError::end_of_file("identifier")));
+
+        let mut tokens: Vec<String> = tokenize!("a.b.;");
+        test_parse!(
+            tokens,
+            Path,
+            Err(ParseError::InvalidToken {
+                token: ";".to_string(),
+                error: "Expected identifier after `.` in path".to_string(),
+                expected: None
+            })
+        );
+
+        let mut tokens: Vec<String> = tokenize!("a.b.c");
+        assert_eq!(Path::parse(&mut tokens).unwrap().to_string(), "a.b.c");
+    }
+
+    #[test]
+    fn test_subscript() {
+        test_parse_exact!("[0]", Subscript, Ok(Subscript(0)));
+        test_parse_exact!("[-1]", Subscript, Ok(Subscript(-1)));
+        test_parse_exact!("[ +2 ]", Subscript, Ok(Subscript(2)));
+        assert_eq!(Subscript(-1).to_string(), "[-1]");
+
+        let mut tokens: Vec<String> = tokenize!("[1.5]");
+        test_parse!(
+            tokens,
+            Subscript,
+            Err(ParseError::invalid("1.5", "Expected integer"))
+        );
+        let mut tokens: Vec<String> = tokenize!("[1");
+        test_parse!(tokens, Subscript, Err(ParseError::end_of_file("]")));
+    }
+
+    #[test]
+    fn test_percent() {
+        test_parse_exact!("50%", Percent, Ok(Percent(0.5)));
+        test_parse_exact!("12.5%", Percent, Ok(Percent(0.125)));
+        test_parse_exact!("-200 %", Percent, Ok(Percent(-2.0)));
+        assert_eq!(Percent(0.125).to_string(), "12.5%");
+        for text in ["7%", "57%", "0.1%", "-200%", "33.3%"] {
+            let percent: Percent = Percent::parse(&mut tokenize!(text)).unwrap();
+            assert_eq!(percent.to_string(), text);
+        }
+
+        let mut tokens: Vec<String> = tokenize!("50");
+        test_parse!(tokens, Percent, Err(ParseError::end_of_file("%")));
+        let mut tokens: Vec<String> = tokenize!("%");
+        test_parse!(
+            tokens,
+            Percent,
+            Err(ParseError::invalid("%", "Invalid literal"))
+        );
+    }
+
+    #[test]
+    fn test_key_value() {
+        let mut tokens: Vec<String> = tokenize!("limit=5 limit 5");
+
+        let pair: KeyValue<Identifier, Literal> = KeyValue::parse(&mut tokens).unwrap();
+        assert_eq!(
+            pair,
+            KeyValue {
+                key: Identifier("limit".to_string()),
+                value: Literal::Integer(5)
+            }
+        );
+        assert_eq!(pair.to_string(), "limit=5");
+        test_parse!(
+            tokens,
+            KeyValue<Identifier, Literal>,
+            Err(ParseError::expected("5", "="))
+        );
+    }
+
+    #[test]
+    fn test_flags() {
+        let mut tokens: Vec<String> = tokenize!("A | B|C D || E");
+
+        let flags: Flags<Identifier> = Flags::parse(&mut tokens).unwrap();
+        assert_eq!(
+            flags,
+            Flags(vec![
+                Identifier("A".to_string()),
+                Identifier("B".to_string()),
+                Identifier("C".to_string())
+            ])
+        );
+        assert_eq!(flags.to_string(), "A | B | C");
+        test_parse!(
+            tokens,
+            Flags<Identifier>,
+            Ok(Flags(vec![Identifier("D".to_string())]))
+        );
+        assert_eq!(tokens.last(), Some(&"||".to_string()));
+
+        let mut tokens: Vec<String> = tokenize!("1 | 2 |");
+        assert!(Flags::<Literal>::parse(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_literal() {
+        let mut tokens: Vec<String> = tokenize!("true false 0 +42 -5 123.0 +8.5 -11.4 \"string\"");
+
+        test_parse!(tokens, Literal, Ok(Literal::Boolean(true)));
+        test_parse!(tokens, Literal, Ok(Literal::Boolean(false)));
+        test_parse!(tokens, Literal, Ok(Literal::Integer(0)));
+        test_parse!(tokens, Literal, Ok(Literal::Integer(42)));
+        test_parse!(tokens, Literal, Ok(Literal::Integer(-5)));
+        test_parse!(tokens, Literal, Ok(Literal::Float(123.0)));
+        test_parse!(tokens, Literal, Ok(Literal::Float(8.5)));
+        test_parse!(tokens, Literal, Ok(Literal::Float(-11.4)));
+        test_parse!(tokens, Literal, Ok(Literal::String("string".to_string())));
+
+        assert!(tokens.is_empty());
+        test_parse!(tokens, Literal, Err(ParseError::end_of_file("literal")));
+    }
+
+    #[test]
+    fn test_boolean_keywords() {
+        let mut tokens: Vec<String> = tokenize!("true false");
+
+        test_parse!(tokens, Literal, Ok(Literal::Boolean(true)));
+        test_parse!(tokens, Identifier, Ok(Identifier("false".to_string())));
+
+        let mut tokens: Vec<String> = tokenize!("true");
+        test_parse!(tokens, Identifier, Ok(Identifier("true".to_string())));
+    }
+
+    #[test]
+    fn test_literal_display() {
+        assert_eq!(Literal::Float(123.0).to_string(), "123.0");
+        assert_eq!(Literal::Float(-8.5).to_string(), "-8.5");
+        assert_eq!(Literal::Integer(123).to_string(), "123");
+
+        for literal in [
+            Literal::Boolean(true),
+            Literal::Integer(-5),
+            Literal::Float(123.0),
+            Literal::Float(-0.25),
+            Literal::Float(1e300),
+            Literal::String("hello world".to_string()),
+        ] {
+            assert_eq!(Literal::parse_str(&literal.to_string()), Ok(literal));
+        }
+    }
+
+    #[test]
+    fn test_literal_string() {
+        let mut tokens: Vec<String> = tokenize!("\"hello world\" \"\" \"a;b\" \"unterminated");
+
+        test_parse!(
+            tokens,
+            Literal,
+            Ok(Literal::String("hello world".to_string()))
+        );
+        test_parse!(tokens, Literal, Ok(Literal::String("".to_string())));
+        test_parse!(tokens, Literal, Ok(Literal::String("a;b".to_string())));
+        test_parse!(
+            tokens,
+            Literal,
+            Err(ParseError::InvalidToken {
+                token: "\"unterminated".to_string(),
+                error: "Unterminated string literal".to_string(),
+                expected: None
+            })
+        );
+
+        let (mut tokens, diagnostics) = crate::tokenize::tokenize_lenient("\"unterminated");
+        tokens.reverse();
+        test_parse!(
+            tokens,
+            Literal,
+            Ok(Literal::String("unterminated".to_string()))
+        );
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_literal_escapes() {
+        let mut tokens: Vec<String> =
+            tokenize!(r#""a\"b\\c" "\n\t\r\0" "\x41\x7a" "\u{1F600}\u{e9}" "\x0""#);
+
+        let literal: Literal = Literal::parse(&mut tokens).unwrap();
+        assert_eq!(literal, Literal::String("a\"b\\c".to_string()));
+        assert_eq!(literal.to_string(), r#""a\"b\\c""#);
+        let literal: Literal = Literal::parse(&mut tokens).unwrap();
+        assert_eq!(literal, Literal::String("\n\t\r\0".to_string()));
+        assert_eq!(literal.to_string(), r#""\n\t\r\0""#);
+        test_parse!(tokens, Literal, Ok(Literal::String("Az".to_string())));
+        test_parse!(
+            tokens,
+            Literal,
+            Ok(Literal::String("\u{1F600}é".to_string()))
+        );
+        test_parse!(
+            tokens,
+            Literal,
+            Err(ParseError::invalid(
+                r#""\x0""#,
+                "Escape `\\x` at index 1 must be followed by exactly two hex digits"
+            ))
+        );
+        assert_eq!(
+            Literal::String("\u{7}".to_string()).to_string(),
+            r#""\u{7}""#
+        );
+
+        for (token, error) in [
+            (r#""ab\q""#, "Unknown escape `\\q` at index 3"),
+            (
+                r#""\xFF""#,
+                "Escape `\\xFF` at index 1 is not ASCII, use `\\u{...}` instead",
+            ),
+            (
+                r#""\u41""#,
+                "Escape `\\u` at index 1 must be followed by a code point in braces, like `\\u{1F600}`",
+            ),
+            (
+                r#""\u{D800}""#,
+                "Invalid code point `\\u{D800}` at index 1 in string literal",
+            ),
+            (
+                r#""\u{110000}""#,
+                "Invalid code point `\\u{110000}` at index 1 in string literal",
+            ),
+            (r#""\u{}""#, "Invalid code point `\\u{}` at index 1 in string literal"),
+            (r#""\u{+41}""#, "Invalid code point `\\u{+41}` at index 1 in string literal"),
+            (r#""a\""#, "Unterminated string literal"),
+        ] {
+            let mut tokens: Vec<String> = tokenize!(token);
+            assert_eq!(tokens.len(), 1, "{token}");
+            test_parse!(tokens, Literal, Err(ParseError::invalid(token, error)));
+        }
+    }
+
+    #[test]
+    fn test_literal_radix() {
+        let mut tokens: Vec<String> = tokenize!("0xFF 0o17 -0b101 0xGG 0o8 0b123 0x");
+
+        test_parse!(tokens, Literal, Ok(Literal::Integer(0xFF)));
+        test_parse!(tokens, Literal, Ok(Literal::Integer(0o17)));
+        test_parse!(tokens, Literal, Ok(Literal::Integer(-0b101)));
+        for (token, error) in [
+            ("0xGG", "Invalid hex digit `G` in literal"),
+            ("0o8", "Invalid octal digit `8` in literal"),
+            ("0b123", "Invalid binary digit `2` in literal"),
+            ("0x", "Missing digits after `0x` in literal"),
+        ] {
+            test_parse!(tokens, Literal, Err(ParseError::invalid(token, error)));
+            tokens.pop();
+        }
+        assert!(tokens.is_empty());
+    }
+
+    #[test]
+    fn test_literal_digit_separators() {
+        let mut tokens: Vec<String> = tokenize!("1_000");
+
+        #[cfg(feature = "strtoint")]
+        test_parse!(tokens, Literal, Ok(Literal::Integer(1000)));
+        #[cfg(not(feature = "strtoint"))]
+        test_parse!(
+            tokens,
+            Literal,
+            Err(ParseError::invalid("1_000", "Invalid literal"))
+        );
+    }
+
+    #[test]
+    fn test_literal_integer_bounds() {
+        let input: String = format!("{} {} -{}0", isize::MIN, isize::MAX, isize::MAX);
+        let mut tokens: Vec<String> = tokenize!(&input);
+
+        test_parse!(tokens, Literal, Ok(Literal::Integer(isize::MIN)));
+        test_parse!(tokens, Literal, Ok(Literal::Integer(isize::MAX)));
+        assert!(matches!(
+            Literal::parse(&mut tokens),
+            Ok(Literal::Float(value)) if value < isize::MIN as f64
+        ));
+    }
+
+    #[test]
+    fn test_literal_special_floats() {
+        let mut tokens: Vec<String> = tokenize!("-0.0 inf nan 1e999");
+
+        let zero: Literal = Literal::parse(&mut tokens).unwrap();
+        assert_eq!(zero, Literal::Float(0.0));
+        assert!(matches!(zero, Literal::Float(value) if value.is_sign_positive()));
+
+        for token in ["inf", "nan", "1e999"] {
+            test_parse!(
+                tokens,
+                Literal,
+                Err(ParseError::InvalidToken {
+                    token: token.to_string(),
+                    error: "Float literals must be finite".to_string(),
+                    expected: None
+                })
+            );
+            tokens.pop();
+        }
+        assert!(tokens.is_empty());
+    }
+
+    #[test]
+    fn test_value() {
+        let mut tokens: Vec<String> = tokenize!("5 -1.5 \"text\" true name @");
+
+        test_parse!(tokens, Value, Ok(Value::Literal(Literal::Integer(5))));
+        test_parse!(tokens, Value, Ok(Value::Literal(Literal::Float(-1.5))));
+        test_parse!(
+            tokens,
+            Value,
+            Ok(Value::Literal(Literal::String("text".to_string())))
+        );
+        test_parse!(tokens, Value, Ok(Value::Literal(Literal::Boolean(true))));
+        let value: Value = Value::parse(&mut tokens).unwrap();
+        assert_eq!(value, Value::Reference(Identifier("name".to_string())));
+        assert_eq!(value.to_string(), "name");
+        test_parse!(
+            tokens,
+            Value,
+            Err(ParseError::invalid("@", "Invalid literal"))
+        );
+        assert_eq!(tokens, vec!["@".to_string()]);
+    }
+
+    #[test]
+    fn test_literal_arithmetic() {
+        use Literal::{Boolean, Float, Integer};
+        let string = |value: &str| Literal::String(value.to_string());
+
+        assert_eq!(Integer(2).add(&Integer(3)), Ok(Integer(5)));
+        assert_eq!(Integer(2).sub(&Float(0.5)), Ok(Float(1.5)));
+        assert_eq!(Float(1.5).mul(&Integer(2)), Ok(Float(3.0)));
+        assert_eq!(Integer(7).div(&Integer(2)), Ok(Integer(3)));
+        assert_eq!(Integer(-7).div(&Integer(2)), Ok(Integer(-3)));
+        assert_eq!(Integer(7).div(&Float(2.0)), Ok(Float(3.5)));
+        assert_eq!(Float(-1.0).mul(&Integer(0)), Ok(Float(0.0)));
+        assert_eq!(string("a").add(&string("b")), Ok(string("ab")));
+
+        assert_eq!(
+            Integer(isize::MAX).add(&Integer(1)),
+            Ok(Float(isize::MAX as f64 + 1.0))
+        );
+        assert_eq!(
+            Integer(isize::MIN).div(&Integer(-1)),
+            Ok(Float(-(isize::MIN as f64)))
+        );
+
+        assert_eq!(
+            Integer(1).div(&Integer(0)),
+            Err(ParseError::invalid("1 / 0", "Division by zero"))
+        );
+        assert_eq!(
+            Float(1.0).div(&Float(0.0)),
+            Err(ParseError::invalid("1.0 / 0.0", "Division by zero"))
+        );
+        assert_eq!(
+            Float(f64::MAX).mul(&Integer(2)),
+            Err(ParseError::invalid(
+                format!("{:?} * 2", f64::MAX),
+                "Result must be finite"
+            ))
+        );
+        assert_eq!(
+            string("a").add(&Integer(1)),
+            Err(ParseError::invalid(
+                "\"a\" + 1",
+                "Operator + needs numeric operands"
+            ))
+        );
+        assert_eq!(
+            Boolean(true).sub(&Integer(1)),
+            Err(ParseError::invalid(
+                "true - 1",
+                "Operator - needs numeric operands"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_literal_truthy() {
+        let truthy: Vec<Literal> = vec![
+            Literal::Boolean(true),
+            Literal::Integer(-1),
+            Literal::Float(0.5),
+            Literal::String("false".to_string()),
+        ];
+        let falsy: Vec<Literal> = vec![
+            Literal::Boolean(false),
+            Literal::Integer(0),
+            Literal::Float(0.0),
+            Literal::String(String::new()),
+        ];
+        assert!(truthy.iter().all(Literal::is_truthy));
+        assert!(!falsy.iter().any(Literal::is_truthy));
+
+        let (yes, no) = (Literal::Integer(5), Literal::String(String::new()));
+        assert_eq!(yes.and(&yes), Literal::Boolean(true));
+        assert_eq!(yes.and(&no), Literal::Boolean(false));
+        assert_eq!(no.or(&yes), Literal::Boolean(true));
+        assert_eq!(no.or(&no), Literal::Boolean(false));
+        assert_eq!(yes.not(), Literal::Boolean(false));
+        assert_eq!(no.not(), Literal::Boolean(true));
+    }
+
+    #[test]
+    fn test_colon_pair() {
+        let mut tokens: Vec<String> = tokenize!("a:1 b: true c : x d:e:f");
+        test_parse!(
+            tokens,
+            ColonPair<Literal>,
+            Ok(ColonPair {
+                key: Identifier("a".to_string()),
+                value: Literal::Integer(1),
+            })
+        );
+        test_parse!(
+            tokens,
+            ColonPair<Literal>,
+            Ok(ColonPair {
+                key: Identifier("b".to_string()),
+                value: Literal::Boolean(true),
+            })
+        );
+        test_parse!(
+            tokens,
+            ColonPair<Identifier>,
+            Ok(ColonPair {
+                key: Identifier("c".to_string()),
+                value: Identifier("x".to_string()),
+            })
+        );
+        let pair: ColonPair<crate::minecraft::ResourceLocation> =
+            ColonPair::parse(&mut tokens).unwrap();
+        assert_eq!(pair.to_string(), "d: e:f");
+        assert!(tokens.is_empty());
+
+        let mut tokens: Vec<String> = tokenize!("a 1 a:");
+        test_parse!(
+            tokens,
+            ColonPair<Literal>,
+            Err(ParseError::expected("1", ":"))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            ColonPair<Literal>,
+            Err(ParseError::end_of_file("identifier"))
+        );
+        let mut tokens: Vec<String> = tokenize!("1:2");
+        test_parse!(
+            tokens,
+            ColonPair<Literal>,
+            Err(ParseError::invalid(
+                "1",
+                "Identifiers must not start with a number and can only contain letters, numbers, and underscores"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        struct Statements;
+        impl Parse for Statements {
+            fn parse(tokens: &mut Vec<String>) -> Result<Self, ParseError> {
+                parse_statements::<KeyValue<Identifier, Literal>>(tokens).map(|_| Statements)
+            }
+        }
+        let parse_str_statements = |source: &str| Statements::parse_str(source).map(|_| ());
+
+        let source: &str = "a = 1;\n\tb = 2;\n  longer 3; c = 4;";
+        let error: ParseError = parse_str_statements(source).unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "error: Invalid token: Expected =: 3\n --> line 3, column 10\n  |\n3 |   longer 3; c = 4;\n  |          ^"
+        );
+
+        let source: String = format!("{}\tx 5", "\n".repeat(9));
+        let error: ParseError = parse_str_statements(&source).unwrap_err();
+        assert_eq!(
+            error.render(&source),
+            "error: Invalid token: Expected =: 5\n  --> line 10, column 4\n   |\n10 | \tx 5\n   | \t  ^"
+        );
+
+        let error: ParseError = Identifier::parse_str("`unterminated").unwrap_err();
+        assert!(error
+            .render("`unterminated")
+            .ends_with("1 | `unterminated\n  | ^^^^^^^^^^^^^"));
+
+        assert_eq!(ParseError::eof().render("a"), "error: Unexpected EOF");
+        let error: ParseError = ParseError::eof().with_span(Span {
+            start: 10,
+            end: 11,
+            line: 1,
+            column: 11,
+        });
+        assert_eq!(error.render("a"), "error: Unexpected EOF");
+
+        let source: &str = "a b }";
+        let error: ParseError = KeyValue::<Identifier, Literal>::parse_str(source)
+            .unwrap_err()
+            .with_context("In block");
+        assert_eq!(
+            error.render(source),
+            "error: In block: Invalid token: Expected =: b\n --> line 1, column 3\n  |\n1 | a b }\n  |   ^"
+        );
+        assert_eq!(
+            error.without_spans(),
+            ParseError::expected("b", "=").with_context("In block")
+        );
+    }
+
+    #[test]
+    fn test_attribute() {
+        test_parse_exact!(
+            "@deprecated",
+            Attribute,
+            Ok(Attribute {
+                name: Identifier("deprecated".to_string()),
+                arguments: vec![],
+            })
+        );
+        test_parse_exact!(
+            "@experimental()",
+            Attribute,
+            Ok(Attribute {
+                name: Identifier("experimental".to_string()),
+                arguments: vec![],
+            })
+        );
+
+        let mut tokens: Vec<String> = tokenize!(r#"@since("1.20", 5, note = "x",) struct"#);
+        let attribute: Attribute = Attribute::parse(&mut tokens).unwrap();
+        assert_eq!(
+            attribute.arguments,
+            vec![
+                AttributeArgument::Positional(Literal::String("1.20".to_string())),
+                AttributeArgument::Positional(Literal::Integer(5)),
+                AttributeArgument::Named(KeyValue {
+                    key: Identifier("note".to_string()),
+                    value: Literal::String("x".to_string()),
+                }),
+            ]
+        );
+        assert_eq!(attribute.to_string(), r#"@since("1.20", 5, note="x")"#);
+        assert_eq!(tokens, tokens!["struct"]);
+
+        let mut tokens: Vec<String> = tokenize!("deprecated @a(b) @");
+        test_parse!(
+            tokens,
+            Attribute,
+            Err(ParseError::expected("deprecated", "@"))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Attribute,
+            Err(ParseError::invalid("b", "Invalid literal"))
+        );
+        tokens.pop();
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Attribute,
+            Err(ParseError::end_of_file("identifier"))
+        );
+    }
+
+    #[test]
+    fn test_literal_accessors() {
+        let literals: Vec<Literal> = vec![
+            Literal::Boolean(true),
+            Literal::Integer(2),
+            Literal::Float(0.5),
+            Literal::String("a".to_string()),
+        ];
+        let names: Vec<&str> = literals.iter().map(Literal::type_name).collect();
+        assert_eq!(names, vec!["boolean", "integer", "float", "string"]);
+
+        assert_eq!(literals[0].as_bool(), Some(true));
+        assert_eq!(literals[1].as_bool(), None);
+        assert_eq!(literals[1].as_integer(), Some(2));
+        assert_eq!(literals[2].as_integer(), None);
+        assert_eq!(literals[1].as_float(), Some(2.0));
+        assert_eq!(literals[2].as_float(), Some(0.5));
+        assert_eq!(literals[3].as_float(), None);
+        assert_eq!(literals[3].as_str(), Some("a"));
+        assert_eq!(literals[0].as_str(), None);
+    }
+
+    /// Tricky inputs for literals, paths, and values, pinning down current behavior.
+    #[test]
+    fn test_parse_corpus() {
+        // Signs are merged into the number, even across whitespace.
+        test_parse_exact!("- 5", Literal, Ok(Literal::Integer(-5)));
+        test_parse_exact!("+5", Literal, Ok(Literal::Integer(5)));
+        test_parse_exact!("-0.0", Literal, Ok(Literal::Float(0.0)));
+        test_parse_exact!(
+            "-0x8000000000000000",
+            Literal,
+            Ok(Literal::Integer(isize::MIN))
+        );
+        test_parse_exact!(
+            "9223372036854775808",
+            Literal,
+            Ok(Literal::Float(9223372036854775808.0))
+        );
+        test_parse_exact!("1.", Literal, Ok(Literal::Float(1.0)));
+        test_parse_exact!(".5", Literal, Ok(Literal::Float(0.5)));
+        test_parse_exact!(
+            r#""\x41\u{42}""#,
+            Literal,
+            Ok(Literal::String("AB".to_string()))
+        );
+
+        let mut tokens: Vec<String> = tokenize!(r#"1..2 inf 0b102 "unterminated"#);
+        test_parse!(
+            tokens,
+            Literal,
+            Err(ParseError::invalid("1..2", "Invalid literal"))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Literal,
+            Err(ParseError::invalid("inf", "Float literals must be finite"))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Literal,
+            Err(ParseError::invalid(
+                "0b102",
+                "Invalid binary digit `2` in literal"
+            ))
+        );
+        tokens.pop();
+        test_parse!(
+            tokens,
+            Literal,
+            Err(ParseError::invalid(
+                "\"unterminated",
+                "Unterminated string literal"
+            ))
+        );
+
+        // `.5` is one token, so it ends a path instead of continuing it.
+        let mut tokens: Vec<String> = tokenize!("a.5 `a b`.c a..b");
+        test_parse!(tokens, Path, Ok(Path(vec![Identifier("a".to_string())])));
+        assert_eq!(tokens.pop(), Some(".5".to_string()));
+        test_parse!(
+            tokens,
+            Path,
+            Ok(Path(vec![
+                Identifier("a b".to_string()),
+                Identifier("c".to_string())
+            ]))
+        );
+        test_parse!(
+            tokens,
+            Path,
+            Err(ParseError::invalid(
+                ".",
+                "Expected identifier after `.` in path"
+            ))
+        );
+
+        test_parse_exact!(
+            "truex",
+            Value,
+            Ok(Value::Reference(Identifier("truex".to_string())))
+        );
+        let mut tokens: Vec<String> = tokenize!("名前");
+        test_parse!(
+            tokens,
+            Value,
+            Err(ParseError::invalid("名前", "Invalid literal"))
+        );
     }
 }
@@ -0,0 +1,115 @@
+//! Module for mapping positions in concatenated sources back to their files.
+
+use std::fmt::Display;
+
+use crate::tokenize::Span;
+
+/// A position in one of the files of a [`SourceMap`].
+/// Lines and columns start at 1, like in [`Span`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location<'a> {
+    pub file: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+impl Display for Location<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Several files concatenated into one source, keeping track of where each file starts.
+/// Tokenize [`SourceMap::source`] as usual, then translate offsets and spans back into
+/// files with [`SourceMap::locate`] and [`SourceMap::locate_span`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    source: String,
+    files: Vec<(String, usize)>,
+}
+impl SourceMap {
+    /// Create an empty source map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a file to the source, returning the byte offset it starts at.
+    /// A newline is added after files that don't end with one, so tokens never span two files.
+    pub fn add_file(&mut self, name: impl Into<String>, text: &str) -> usize {
+        let start: usize = self.source.len();
+        self.files.push((name.into(), start));
+        self.source += text;
+        if !text.is_empty() && !text.ends_with('\n') {
+            self.source.push('\n');
+        }
+        start
+    }
+
+    /// The concatenated source of all files.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The file containing the given byte offset, and where it is in that file.
+    /// The end of the source belongs to the last file.
+    /// Returns `None` if the offset is past the end of the source, or there are no files.
+    pub fn locate(&self, offset: usize) -> Option<Location<'_>> {
+        if offset > self.source.len() {
+            return None;
+        }
+        let index: usize = self
+            .files
+            .partition_point(|(_, start)| *start <= offset)
+            .checked_sub(1)?;
+        let (file, start) = &self.files[index];
+
+        let before: &str = &self.source[*start..offset];
+        let line_start: usize = before.rfind('\n').map_or(0, |i| i + 1);
+        Some(Location {
+            file,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        })
+    }
+
+    /// The file containing the start of the given span, and where it is in that file.
+    pub fn locate_span(&self, span: Span) -> Option<Location<'_>> {
+        self.locate(span.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tokenize::{Token, Tokenizer};
+
+    use super::*;
+
+    #[test]
+    fn test_source_map() {
+        let mut map: SourceMap = SourceMap::new();
+        assert_eq!(map.locate(0), None);
+        assert_eq!(map.add_file("a.spec", "x = 1;\ny = 2;"), 0);
+        assert_eq!(map.add_file("empty.spec", ""), 14);
+        assert_eq!(map.add_file("b.spec", "z\n  w;\n"), 14);
+        assert_eq!(map.source(), "x = 1;\ny = 2;\nz\n  w;\n");
+
+        let tokens: Vec<Token> = Tokenizer::new(map.source()).collect();
+        let locate = |text: &str| {
+            let token: &Token = tokens.iter().find(|token| token.text == text).unwrap();
+            map.locate_span(token.span).unwrap().to_string()
+        };
+        assert_eq!(locate("x"), "a.spec:1:1");
+        assert_eq!(locate("2"), "a.spec:2:5");
+        assert_eq!(locate("z"), "b.spec:1:1");
+        assert_eq!(locate("w"), "b.spec:2:3");
+
+        assert_eq!(
+            map.locate(map.source().len()),
+            Some(Location {
+                file: "b.spec",
+                line: 3,
+                column: 1
+            })
+        );
+        assert_eq!(map.locate(map.source().len() + 1), None);
+    }
+}
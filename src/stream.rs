@@ -0,0 +1,547 @@
+//! Module for walking tokens with trivia, like comments, kept but skipped over.
+
+use crate::{
+    ensure,
+    parse::{Atomic, Parse, ParseError},
+    tokenize::{assemble_doc, Token, TokenKind, Tokenizer},
+};
+
+/// A saved position in a [`TokenStream`], from [`TokenStream::checkpoint`].
+/// Checkpoints are only meaningful for the stream they were taken from, or clones of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checkpoint(usize);
+
+/// A value from [`TokenStream::parse_commented_list`], with the comments attached to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commented<T> {
+    /// Comments between the previous separator (or the opening delimiter) and the value.
+    pub leading: Vec<Token>,
+    pub value: T,
+    /// Comments between the value and the separator after it, and comments that end the line
+    /// of that separator, like `// one` in `1, // one`.
+    pub trailing: Vec<Token>,
+}
+
+/// A list from [`TokenStream::parse_commented_list`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentedList<T> {
+    pub items: Vec<Commented<T>>,
+    /// Comments that don't belong to any item, because no item comes after them,
+    /// like `/* end */` in `[1, /* end */]` or `[/* empty */]`.
+    pub dangling: Vec<Token>,
+}
+
+/// A stream of tokens that keeps trivia, but skips it by default.
+///
+/// Comments are trivia. Newline tokens (from [`Tokenizer::newlines`]) are significant unless
+/// [`TokenStream::skip_newlines`] is set. [`Iterator::next`] and [`TokenStream::peek`] only
+/// see significant tokens, so grammar code never has to handle trivia.
+/// [`TokenStream::next_raw`], [`TokenStream::peek_raw`], and [`TokenStream::tokens`] see
+/// everything, for tools like formatters.
+///
+/// Streams are `Clone`, and [`TokenStream::checkpoint`] saves the position cheaply, so parsing can
+/// be resumed from a known point instead of restarting. For example, an editor can cache a
+/// checkpoint after each top-level item, and re-parse only from the last one before an edit:
+///
+/// ```
+/// use specmc_base::{parse::Identifier, stream::{Checkpoint, TokenStream}};
+///
+/// let mut stream: TokenStream = TokenStream::new("a // first\nb c");
+/// let mut checkpoints: Vec<Checkpoint> = vec![stream.checkpoint()];
+/// while !stream.is_empty() {
+///     stream.parse::<Identifier>().unwrap();
+///     checkpoints.push(stream.checkpoint());
+/// }
+///
+/// // Resume from just after `a`.
+/// stream.restore(checkpoints[1]);
+/// assert_eq!(stream.parse::<Identifier>(), Ok(Identifier("b".to_string())));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenStream {
+    /// The text the tokens came from, for [`TokenStream::parse_raw_until`].
+    source: String,
+    tokens: Vec<Token>,
+    position: usize,
+    skip_newlines: bool,
+    /// The remaining significant tokens as a reversed token list, kept in step with the cursor,
+    /// so [`TokenStream::parse`] doesn't have to build it on each call.
+    parse_tokens: Vec<String>,
+}
+impl TokenStream {
+    /// Tokenize the input into a stream, keeping comments.
+    pub fn new(input: &str) -> Self {
+        let mut stream: TokenStream = Self::from_tokens(Tokenizer::new(input).collect());
+        stream.source = input.to_string();
+        stream
+    }
+
+    /// Create a stream from already tokenized input.
+    /// The whitespace between tokens isn't known, so the source text is rebuilt from the tokens
+    /// with spaces in the gaps between their spans.
+    pub fn from_tokens(tokens: Vec<Token>) -> Self {
+        let mut source: String = String::new();
+        for token in &tokens {
+            let gap: usize = token.span.start.saturating_sub(source.len());
+            source.push_str(&" ".repeat(gap));
+            source.push_str(&token.text);
+        }
+        let mut stream: TokenStream = TokenStream {
+            source,
+            tokens,
+            position: 0,
+            skip_newlines: false,
+            parse_tokens: vec![],
+        };
+        stream.sync_parse_tokens();
+        stream
+    }
+
+    /// Set whether newline tokens are treated as trivia.
+    pub fn skip_newlines(mut self, skip_newlines: bool) -> Self {
+        self.skip_newlines = skip_newlines;
+        self.sync_parse_tokens();
+        self
+    }
+
+    /// Rebuild the token list for [`Parse`] from the cursor.
+    fn sync_parse_tokens(&mut self) {
+        self.parse_tokens = self
+            .remaining()
+            .iter()
+            .rev()
+            .filter(|token| !self.is_trivia(token))
+            .map(|token| token.text.clone())
+            .collect();
+    }
+
+    /// Whether the token is trivia, which [`Iterator::next`] and [`TokenStream::peek`] skip.
+    pub fn is_trivia(&self, token: &Token) -> bool {
+        match token.kind() {
+            TokenKind::Comment | TokenKind::DocComment => true,
+            TokenKind::Newline => self.skip_newlines,
+            _ => false,
+        }
+    }
+
+    /// The index of the next significant token, at or after the cursor.
+    fn next_significant(&self) -> Option<usize> {
+        (self.position..self.tokens.len()).find(|&i| !self.is_trivia(&self.tokens[i]))
+    }
+
+    /// Move the cursor past the next significant token, without updating the token list
+    /// for [`Parse`], and return its index.
+    fn advance(&mut self) -> Option<usize> {
+        let index: usize = self.next_significant()?;
+        self.position = index + 1;
+        Some(index)
+    }
+
+    /// Look at the next significant token without consuming it.
+    pub fn peek(&self) -> Option<&Token> {
+        self.next_significant().map(|i| &self.tokens[i])
+    }
+
+    /// Look at the next token, including trivia, without consuming it.
+    pub fn peek_raw(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    /// The doc comments in the trivia before the next significant token, assembled into one
+    /// doc string with [`assemble_doc`]. Call this before parsing a declaration to get its docs.
+    pub fn doc_comment(&self) -> Option<String> {
+        let end: usize = self.next_significant().unwrap_or(self.tokens.len());
+        assemble_doc(&self.tokens[self.position..end])
+    }
+
+    /// Consume the next token, including trivia.
+    pub fn next_raw(&mut self) -> Option<Token> {
+        let token: Token = self.tokens.get(self.position)?.clone();
+        self.position += 1;
+        if !self.is_trivia(&token) {
+            self.parse_tokens.pop();
+        }
+        Some(token)
+    }
+
+    /// Save the current position, to go back to with [`TokenStream::restore`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.position)
+    }
+
+    /// Go back (or forward) to a saved position.
+    ///
+    /// # Panics
+    /// Panics if the checkpoint is past the end of the stream.
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        assert!(
+            checkpoint.0 <= self.tokens.len(),
+            "Checkpoint is past the end of the stream"
+        );
+        self.position = checkpoint.0;
+        self.sync_parse_tokens();
+    }
+
+    /// Parse a value from the remaining significant tokens, and move past the tokens it consumed.
+    /// If parsing fails, the stream is left where it was.
+    /// The token list for [`Parse`] is kept in step with the cursor, so this doesn't rebuild it
+    /// from the remaining tokens on each call.
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    pub fn parse<T: Parse>(&mut self) -> Result<T, ParseError> {
+        let before: usize = self.parse_tokens.len();
+        let Atomic(value) = Atomic::<T>::parse(&mut self.parse_tokens)?;
+        for _ in 0..before.saturating_sub(self.parse_tokens.len()) {
+            self.advance();
+        }
+        // A parse may leave part of a token it split behind, which the stream can't represent.
+        if self.parse_tokens.last() != self.peek().map(|token| &token.text) {
+            self.sync_parse_tokens();
+        }
+        Ok(value)
+    }
+
+    /// Take the source text from the cursor up to the next `delimiter` token, without tokenizing it.
+    /// This is for embedded payloads, like a JSON blob, that are parsed with something else.
+    ///
+    /// The text starts right after the last consumed token, so any whitespace and comments
+    /// are kept as written. The delimiter is not consumed.
+    /// If the delimiter never appears, [`ParseError::EndOfFile`] expecting the delimiter is returned,
+    /// and the stream is left where it was.
+    ///
+    /// ```
+    /// use specmc_base::stream::TokenStream;
+    ///
+    /// let mut stream: TokenStream = TokenStream::new("json { \"a\": [1,  2] } end");
+    /// stream.next();
+    /// stream.next();
+    /// assert_eq!(stream.parse_raw_until("end").unwrap(), " \"a\": [1,  2] } ");
+    /// ```
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    pub fn parse_raw_until(&mut self, delimiter: &str) -> Result<String, ParseError> {
+        let end: usize = (self.position..self.tokens.len())
+            .find(|&i| self.tokens[i].text == delimiter)
+            .ok_or_else(|| ParseError::end_of_file(delimiter))?;
+        let start: usize = match self.position.checked_sub(1) {
+            Some(previous) => self.tokens[previous].span.end,
+            None => self.tokens[self.position].span.start,
+        };
+
+        let raw: String = self.source[start..self.tokens[end].span.start].to_string();
+        while self.position < end {
+            self.next_raw();
+        }
+        Ok(raw)
+    }
+
+    /// Parse a delimited list like [`crate::parse::parse_delimited_list`], but keep the comments
+    /// between items, so a formatter can write them back in the same place.
+    ///
+    /// Comments before an item are its [`Commented::leading`] comments. Comments after an item,
+    /// up to its separator, are its [`Commented::trailing`] comments, and so are comments after
+    /// the separator if nothing else comes after them on that line. So in
+    ///
+    /// ```text
+    /// [
+    ///     /* a */ 1, // one
+    ///     // b
+    ///     2 /* two */
+    /// ]
+    /// ```
+    ///
+    /// `/* a */` leads `1`, `// one` trails `1`, `// b` leads `2`, and `/* two */` trails `2`.
+    /// On one line, like `[1, /* a */ 2]`, comments after a separator lead the next item.
+    /// Comments after the last separator with no item after them are [`CommentedList::dangling`].
+    /// Comments inside an item are skipped by [`TokenStream::parse`] as usual, and are not kept.
+    ///
+    /// If parsing fails, the stream is left where it was.
+    #[must_use = "parsing may fail after consuming tokens, so the result should be checked"]
+    pub fn parse_commented_list<T: Parse>(
+        &mut self,
+        open: &str,
+        separator: &str,
+        close: &str,
+        allow_trailing: bool,
+    ) -> Result<CommentedList<T>, ParseError> {
+        let start: Checkpoint = self.checkpoint();
+        self.commented_list(open, separator, close, allow_trailing)
+            .inspect_err(|_| self.restore(start))
+    }
+
+    /// The body of [`TokenStream::parse_commented_list`], without restoring the stream on failure.
+    fn commented_list<T: Parse>(
+        &mut self,
+        open: &str,
+        separator: &str,
+        close: &str,
+        allow_trailing: bool,
+    ) -> Result<CommentedList<T>, ParseError> {
+        let expected: String = format!("{separator} or {close}");
+        match self.next() {
+            Some(token) if token.text == open => {}
+            Some(token) => return Err(ParseError::expected(token.text, open)),
+            None => return Err(ParseError::end_of_file(open)),
+        }
+
+        let mut items: Vec<Commented<T>> = vec![];
+        loop {
+            let leading: Vec<Token> = self.take_trivia(None);
+            if self.peek().is_some_and(|token| token.text == close) {
+                self.next();
+                return Ok(CommentedList {
+                    items,
+                    dangling: leading,
+                });
+            }
+
+            let value: T = self.parse()?;
+            let mut trailing: Vec<Token> = self.take_trivia(None);
+            let token: Token = self
+                .next()
+                .ok_or_else(|| ParseError::end_of_file(&expected))?;
+            if token.text != close {
+                ensure!(
+                    token.text == separator,
+                    ParseError::expected(token.text, &expected)
+                );
+                let ends_line: bool = self
+                    .peek()
+                    .is_none_or(|next| next.span.line != token.span.line);
+                if ends_line {
+                    trailing.extend(self.take_trivia(Some(token.span.line)));
+                }
+            }
+            items.push(Commented {
+                leading,
+                value,
+                trailing,
+            });
+
+            if token.text == close {
+                return Ok(CommentedList {
+                    items,
+                    dangling: vec![],
+                });
+            }
+            ensure!(
+                allow_trailing || self.peek().is_none_or(|token| token.text != close),
+                ParseError::invalid(
+                    close,
+                    format!("Trailing {separator} is not allowed before {close}")
+                )
+            );
+        }
+    }
+
+    /// Consume the trivia at the cursor, only on the given line if there is one.
+    fn take_trivia(&mut self, line: Option<usize>) -> Vec<Token> {
+        let mut trivia: Vec<Token> = vec![];
+        while let Some(token) = self.peek_raw() {
+            if !self.is_trivia(token) || line.is_some_and(|line| token.span.line != line) {
+                break;
+            }
+            trivia.push(self.next_raw().unwrap());
+        }
+        trivia
+    }
+
+    /// All tokens in the stream, including trivia and tokens that were already consumed.
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// The tokens that haven't been consumed yet, including trivia.
+    pub fn remaining(&self) -> &[Token] {
+        &self.tokens[self.position..]
+    }
+
+    /// Whether there are no significant tokens left.
+    pub fn is_empty(&self) -> bool {
+        self.peek().is_none()
+    }
+
+    /// The remaining significant tokens, as a reversed token list for [`crate::parse::Parse`].
+    pub fn to_parse_tokens(&self) -> Vec<String> {
+        self.parse_tokens.clone()
+    }
+}
+impl Iterator for TokenStream {
+    type Item = Token;
+
+    /// Consume the next significant token, skipping any trivia before it.
+    fn next(&mut self) -> Option<Token> {
+        let index: usize = self.advance()?;
+        self.parse_tokens.pop();
+        Some(self.tokens[index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse::{Identifier, Literal},
+        tokens,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_token_stream() {
+        let mut stream: TokenStream = TokenStream::new("a // one\n/* two */ b /// three");
+
+        assert_eq!(stream.peek().map(Token::text), Some("a"));
+        assert_eq!(stream.next().map(|token| token.text), Some("a".to_string()));
+        assert_eq!(stream.peek_raw().map(Token::text), Some("// one"));
+        assert_eq!(stream.peek().map(Token::text), Some("b"));
+        assert_eq!(stream.to_parse_tokens(), vec!["b".to_string()]);
+        assert_eq!(stream.next().map(|token| token.text), Some("b".to_string()));
+        assert!(stream.is_empty());
+        assert_eq!(stream.remaining().len(), 1);
+        assert_eq!(
+            stream.next_raw().map(|token| token.text),
+            Some("/// three".to_string())
+        );
+        assert_eq!(stream.next(), None);
+        assert_eq!(stream.tokens().len(), 5);
+    }
+
+    #[test]
+    fn test_doc_comment() {
+        let mut stream: TokenStream = TokenStream::new("/// The x.\n///   Really.\nx\n// y\ny");
+        assert_eq!(stream.doc_comment(), Some("The x.\n  Really.".to_string()));
+        stream.next();
+        assert_eq!(stream.doc_comment(), None);
+    }
+
+    #[test]
+    fn test_skip_newlines() {
+        let tokens: Vec<Token> = Tokenizer::new("a\n// c\nb").newlines(true).collect();
+
+        let texts =
+            |stream: TokenStream| -> Vec<String> { stream.map(|token| token.text).collect() };
+        assert_eq!(
+            texts(TokenStream::from_tokens(tokens.clone())),
+            vec!["a", "\n", "\n", "b"]
+        );
+        assert_eq!(
+            texts(TokenStream::from_tokens(tokens).skip_newlines(true)),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn test_parse_raw_until() -> Result<(), ParseError> {
+        let mut stream: TokenStream =
+            TokenStream::new("raw <{ \"a\": 1, // kept\n  \">\": [] }> after");
+        stream.next();
+        stream.next();
+
+        assert_eq!(
+            stream.parse_raw_until(">")?,
+            "{ \"a\": 1, // kept\n  \">\": [] }"
+        );
+        assert_eq!(stream.next().map(|token| token.text), Some(">".to_string()));
+        assert_eq!(
+            stream.parse_raw_until(">"),
+            Err(ParseError::end_of_file(">"))
+        );
+        assert_eq!(stream.peek().map(Token::text), Some("after"));
+
+        let mut stream: TokenStream = TokenStream::new("  a b;");
+        assert_eq!(stream.parse_raw_until(";")?, "a b");
+
+        let tokens: Vec<Token> = Tokenizer::new("x  a\tb ;").collect();
+        let mut stream: TokenStream = TokenStream::from_tokens(tokens);
+        stream.next();
+        assert_eq!(stream.parse_raw_until(";")?, "  a b ");
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let mut stream: TokenStream = TokenStream::new("a /* b */ b 5");
+
+        let start: Checkpoint = stream.checkpoint();
+        assert_eq!(
+            stream.parse::<Identifier>(),
+            Ok(Identifier("a".to_string()))
+        );
+        let after_a: Checkpoint = stream.checkpoint();
+        let saved: TokenStream = stream.clone();
+        assert_eq!(
+            stream.parse::<Identifier>(),
+            Ok(Identifier("b".to_string()))
+        );
+        assert!(stream.parse::<Identifier>().is_err());
+        assert_eq!(stream.peek().map(Token::text), Some("5"));
+
+        stream.restore(after_a);
+        assert_eq!(stream, saved);
+        stream.restore(start);
+        assert_eq!(stream.peek().map(Token::text), Some("a"));
+        assert_eq!(stream.to_parse_tokens(), tokens!["a", "b", "5"]);
+    }
+
+    #[test]
+    fn test_parse_tokens_follow_cursor() {
+        use crate::parse::ColonPair;
+
+        let mut stream: TokenStream = TokenStream::new("a /* b */ b:1 c d");
+        assert!(stream.parse::<Identifier>().is_ok());
+        assert_eq!(
+            stream.next_raw().map(|token| token.text),
+            Some("/* b */".to_string())
+        );
+        assert!(stream.parse::<ColonPair<Literal>>().is_ok());
+        assert_eq!(stream.to_parse_tokens(), tokens!["c", "d"]);
+        assert!(stream.parse::<Literal>().is_err());
+        assert_eq!(stream.next().map(|token| token.text), Some("c".to_string()));
+        assert_eq!(stream.to_parse_tokens(), tokens!["d"]);
+    }
+
+    #[test]
+    fn test_parse_commented_list() -> Result<(), ParseError> {
+        let source: &str = "[\n    /* a */ 1, // one\n    // b\n    2 /* two */\n] rest";
+        let mut stream: TokenStream = TokenStream::new(source);
+        let list: CommentedList<Literal> = stream.parse_commented_list("[", ",", "]", true)?;
+
+        let texts = |tokens: &[Token]| -> Vec<String> {
+            tokens.iter().map(|token| token.text.clone()).collect()
+        };
+        assert_eq!(list.items.len(), 2);
+        assert_eq!(list.items[0].value, Literal::Integer(1));
+        assert_eq!(texts(&list.items[0].leading), vec!["/* a */"]);
+        assert_eq!(texts(&list.items[0].trailing), vec!["// one"]);
+        assert_eq!(texts(&list.items[1].leading), vec!["// b"]);
+        assert_eq!(texts(&list.items[1].trailing), vec!["/* two */"]);
+        assert!(list.dangling.is_empty());
+        assert_eq!(stream.peek().map(Token::text), Some("rest"));
+
+        let mut stream: TokenStream =
+            TokenStream::new("[1, /* a */ 2] [1, /* end */] [/* empty */]");
+        let list: CommentedList<Literal> = stream.parse_commented_list("[", ",", "]", true)?;
+        assert!(list.items[0].trailing.is_empty());
+        assert_eq!(texts(&list.items[1].leading), vec!["/* a */"]);
+        let list: CommentedList<Literal> = stream.parse_commented_list("[", ",", "]", true)?;
+        assert!(list.items[0].trailing.is_empty());
+        assert_eq!(texts(&list.dangling), vec!["/* end */"]);
+        let list: CommentedList<Literal> = stream.parse_commented_list("[", ",", "]", true)?;
+        assert!(list.items.is_empty());
+        assert_eq!(texts(&list.dangling), vec!["/* empty */"]);
+
+        let mut stream: TokenStream = TokenStream::new("[1 /* x */ 2]");
+        assert_eq!(
+            stream.parse_commented_list::<Literal>("[", ",", "]", true),
+            Err(ParseError::expected("2", ", or ]"))
+        );
+        assert_eq!(stream.peek().map(Token::text), Some("["));
+        let mut stream: TokenStream = TokenStream::new("[1,]");
+        assert_eq!(
+            stream.parse_commented_list::<Literal>("[", ",", "]", false),
+            Err(ParseError::invalid(
+                "]",
+                "Trailing , is not allowed before ]"
+            ))
+        );
+        Ok(())
+    }
+}
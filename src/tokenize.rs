@@ -1,5 +1,9 @@
 //! Module for tokenizing strings.
 
+use std::{borrow::Cow, fmt::Display, iter::FusedIterator, ops::Range};
+
+use thiserror::Error;
+
 #[macro_export]
 macro_rules! tokenize {
     ($input:expr) => {
@@ -10,49 +14,1483 @@ macro_rules! tokenize {
     };
 }
 
+/// Build a token list ready for parsing, from tokens written in source order.
+/// The list is reversed, so `tokens!["a", "b", "c"]` produces `["c", "b", "a"]`,
+/// and `"a"` is the first token to be parsed.
+#[macro_export]
+macro_rules! tokens {
+    ($($token:expr),* $(,)?) => {{
+        let mut tokens: Vec<String> = vec![$($token.to_string()),*];
+        tokens.reverse();
+        tokens
+    }};
+}
+
+/// Assert that the input tokenizes into exactly the given tokens, written in source order.
+/// Comments are skipped, like in [`tokenize`].
+/// On mismatch, the panic message shows where the tokens first differ.
+///
+/// ```
+/// use specmc_base::assert_tokens;
+///
+/// assert_tokens!("a==b", ["a", "==", "b"]);
+/// ```
+#[macro_export]
+macro_rules! assert_tokens {
+    ($input:expr, [$($token:expr),* $(,)?]) => {
+        let expected: &[&str] = &[$($token),*];
+        let found: Vec<String> = $crate::tokenize::tokenize($input);
+        if let Some(diff) = $crate::tokenize::token_diff(&found, expected) {
+            panic!("Tokens of {:?} don't match\n{diff}", $input);
+        }
+    };
+}
+
+/// Describe how `found` differs from `expected`, for [`assert_tokens!`].
+/// Returns `None` if they are the same.
+#[doc(hidden)]
+pub fn token_diff(found: &[String], expected: &[&str]) -> Option<String> {
+    let index: usize = (0..found.len().max(expected.len()))
+        .find(|&i| found.get(i).map(String::as_str) != expected.get(i).copied())?;
+    let show = |token: Option<&str>| {
+        token.map_or("end of input".to_string(), |token| {
+            format!("`{}`", escape_token(token))
+        })
+    };
+    let list = |tokens: Vec<&str>| -> String {
+        let tokens: Vec<String> = tokens.into_iter().map(escape_token).collect();
+        format!("[{}]", tokens.join(", "))
+    };
+
+    Some(format!(
+        "first difference at index {index}: expected {}, found {}\nexpected: {}\n   found: {}",
+        show(expected.get(index).copied()),
+        show(found.get(index).map(String::as_str)),
+        list(expected.to_vec()),
+        list(found.iter().map(String::as_str).collect()),
+    ))
+}
+
 const SPECIAL_CHARS: &[&str] = &[
     " ", "\t", "\n", "\r", "==", "!=", "||", "&&", "**", "(", ")", "{", "}", "[", "]", ",", "=",
-    "-", "+", ";",
-    "\"",
-    // ".", ":", "*", "/", "%", "!", "&", "|", "^", "~", removed because useless
+    "-", "+", ";", ".", "#", "@", "|", "~", "^", "*", "!", "%",
+    // ":", "/", "&", removed because useless
 ];
 
+/// The strings the tokenizer treats as special, in the order they are matched.
+/// Special strings split tokens, and are tokens themselves unless they are whitespace.
+/// String literals and numeric dots (like in `1.5`) are handled separately, and are not included.
+/// Longer strings are matched first, so `***` is `**` followed by `*`.
+/// The sign of an exponent in a number, like in `1e-5`, doesn't split the number.
+/// `~` and `^` directly followed by a number, like in `~-2`, are kept together as one token.
+pub fn special_chars() -> &'static [&'static str] {
+    SPECIAL_CHARS
+}
+
+/// The length of a string literal after its opening quote, including the closing quote.
+/// Escaped characters, like in `\"`, don't end the string.
+/// Returns `None` if the string is unterminated, in which case it runs to the end of the input.
+fn string_length(string: &str, quote: char) -> Option<usize> {
+    let mut chars = string.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some(i + 1),
+            '\\' => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The length of a comment at the start of the input, if there is one.
+/// Line comments don't include the line break they end at.
+fn comment_length(input: &str) -> Option<usize> {
+    if input.starts_with("//") {
+        Some(input.find(['\n', '\r']).unwrap_or(input.len()))
+    } else {
+        input
+            .strip_prefix("/*")
+            .map(|comment| comment.find("*/").map_or(input.len(), |end| end + 4))
+    }
+}
+
+/// The length of a `~` or `^` with a number directly after it, like `~-2` or `^.5`.
+fn coordinate_length(input: &str) -> Option<usize> {
+    let number: &str = input.strip_prefix(['~', '^'])?;
+    let unsigned: &str = number.strip_prefix(['-', '+']).unwrap_or(number);
+    let length: usize = unsigned
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(unsigned.len());
+    unsigned[..length]
+        .contains(|c: char| c.is_ascii_digit())
+        .then(|| input.len() - unsigned.len() + length)
+}
+
+/// A range of bytes in the input, along with the line and column it starts at.
+/// Lines and columns start at 1. Lines can end with `\n`, `\r\n`, or a lone `\r`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A token, along with where it was found in the input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub text: String,
+    pub span: Span,
+}
+impl Display for Token {
+    /// Write the token text, with whitespace and control characters escaped by [`escape_token`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", escape_token(&self.text))
+    }
+}
+impl Token {
+    /// The text of the token.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Where the token was found in the input.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// What kind of token this is.
+    pub fn kind(&self) -> TokenKind {
+        TokenKind::of(&self.text)
+    }
+
+    /// The byte range of the token in the input.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.span.start..self.span.end
+    }
+
+    /// The length of the token in bytes.
+    pub fn len(&self) -> usize {
+        self.span.end - self.span.start
+    }
+
+    /// Whether the token is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A token that borrows its text from the input, from [`Tokenizer::try_next_borrowed`].
+/// The text is only owned when it differs from the input, like an unterminated string
+/// that was closed in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BorrowedToken<'a> {
+    pub text: Cow<'a, str>,
+    pub span: Span,
+}
+impl BorrowedToken<'_> {
+    /// What kind of token this is.
+    pub fn kind(&self) -> TokenKind {
+        TokenKind::of(&self.text)
+    }
+
+    /// Copy the text out of the input, making an owned [`Token`].
+    pub fn into_owned(self) -> Token {
+        Token {
+            text: self.text.into_owned(),
+            span: self.span,
+        }
+    }
+}
+
+/// Make a token readable when debugging, by escaping whitespace and control characters,
+/// like a newline token as `\n`. A lone space is written as `\s`.
+/// Other characters, including the contents of string literals, are left as they are.
+pub fn escape_token(text: &str) -> String {
+    if text == " " {
+        return "\\s".to_string();
+    }
+    text.chars()
+        .map(|c| match c {
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\t' => "\\t".to_string(),
+            c if c.is_control() => c.escape_unicode().to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// What kind of token a token is, judging by its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A `// ...` or `/* ... */` comment.
+    Comment,
+    /// A `/// ...` doc comment.
+    DocComment,
+    /// A line break, if the tokenizer was asked to return them.
+    Newline,
+    /// A string literal, including its quotes.
+    String,
+    /// A number, starting with a digit, or with a `.` followed by a digit.
+    Number,
+    /// One of the special characters, or a coordinate like `~1`.
+    Special,
+    /// Anything else, like identifiers and keywords.
+    Word,
+}
+impl TokenKind {
+    /// The kind of the given token text.
+    pub fn of(text: &str) -> Self {
+        if text.starts_with("///") && !text.starts_with("////") {
+            TokenKind::DocComment
+        } else if text.starts_with("//") || text.starts_with("/*") {
+            TokenKind::Comment
+        } else if text == "\n" {
+            TokenKind::Newline
+        } else if text.starts_with('"') {
+            TokenKind::String
+        } else if text.starts_with(|c: char| c.is_ascii_digit())
+            || text
+                .strip_prefix('.')
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            TokenKind::Number
+        } else if SPECIAL_CHARS.contains(&text) || coordinate_length(text).is_some() {
+            TokenKind::Special
+        } else {
+            TokenKind::Word
+        }
+    }
+
+    /// Whether this is a comment or doc comment.
+    pub fn is_comment(self) -> bool {
+        matches!(self, TokenKind::Comment | TokenKind::DocComment)
+    }
+}
+
+/// Assemble doc comments into one doc string, like the `///` lines before a declaration.
+/// Tokens that aren't doc comments are ignored.
+///
+/// The `///` of each line is removed, then the indentation common to all non-blank lines,
+/// so `/// a` and `///a` both become `a`, but nested indentation is kept.
+/// Trailing whitespace and leading and trailing blank lines are removed, and lines are joined
+/// with `\n`. Returns `None` if there are no doc comments.
+pub fn assemble_doc<'t>(tokens: impl IntoIterator<Item = &'t Token>) -> Option<String> {
+    let lines: Vec<&str> = tokens
+        .into_iter()
+        .filter(|token| token.kind() == TokenKind::DocComment)
+        .map(|token| token.text[3..].trim_end())
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let indentation: usize = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indentation..).unwrap_or(""))
+        .collect();
+
+    let first: usize = lines.iter().position(|line| !line.is_empty()).unwrap_or(0);
+    let last: usize = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(0, |i| i + 1);
+    Some(lines[first..last.max(first)].join("\n"))
+}
+
+/// The default number of columns between tab stops.
+pub const DEFAULT_TAB_WIDTH: usize = 1;
+
+/// A stateful tokenizer.
+/// This holds the input and a byte cursor into it, and produces tokens one at a time.
+#[derive(Debug, Clone)]
+pub struct Tokenizer<'a> {
+    input: &'a str,
+    position: usize,
+    line: usize,
+    column: usize,
+    tab_width: usize,
+    newlines: bool,
+    max_token_length: Option<usize>,
+    lenient: bool,
+    reject_control_chars: bool,
+    diagnostics: Vec<TokenizeError>,
+    operators: Vec<String>,
+    /// Whether [`Tokenizer::next_token`] hit an error, after which it returns `None`.
+    stopped: bool,
+}
+impl<'a> Tokenizer<'a> {
+    /// Create a tokenizer for the given input.
+    /// If the input starts with a byte order mark or a shebang line (`#!...`),
+    /// the cursor starts after them.
+    pub fn new(input: &'a str) -> Self {
+        let mut tokenizer: Tokenizer = Tokenizer {
+            input,
+            position: 0,
+            line: 1,
+            column: 1,
+            tab_width: DEFAULT_TAB_WIDTH,
+            newlines: false,
+            max_token_length: None,
+            lenient: false,
+            reject_control_chars: false,
+            diagnostics: vec![],
+            operators: vec![],
+            stopped: false,
+        };
+        // A byte order mark isn't part of the text, so it doesn't count as a column.
+        if input.starts_with('\u{FEFF}') {
+            tokenizer.position = '\u{FEFF}'.len_utf8();
+        }
+        if input[tokenizer.position..].starts_with("#!") {
+            let rest: &str = &input[tokenizer.position..];
+            tokenizer.advance(rest.find('\n').unwrap_or(rest.len()));
+        }
+        tokenizer
+    }
+
+    /// Set the number of columns between tab stops, used when computing columns of spans.
+    /// A tab advances the column to the next tab stop, so with the default width of 1,
+    /// a tab counts as a single column, like any other character. A width of 0 is treated as 1.
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    /// Set whether line breaks are returned as `\n` tokens, for grammars where newlines are significant.
+    /// By default, they are skipped like other whitespace.
+    pub fn newlines(mut self, newlines: bool) -> Self {
+        self.newlines = newlines;
+        self
+    }
+
+    /// Set the maximum length of a token in bytes, to guard against pathological input.
+    /// By default, tokens can be any length.
+    pub fn max_token_length(mut self, max_token_length: usize) -> Self {
+        self.max_token_length = Some(max_token_length);
+        self
+    }
+
+    /// Set whether to recover from errors in the input, for editors where it is half-typed.
+    ///
+    /// In lenient mode, an unterminated string literal or quoted identifier runs to the end of
+    /// the input, and the missing closing quote is added to the token text, so it parses normally.
+    /// The token's span still only covers the input. Each recovery is recorded in
+    /// [`Tokenizer::diagnostics`]. By default, the token is left unterminated,
+    /// so parsing it fails.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Set whether control characters outside of strings and comments are errors, to catch
+    /// copy-paste artifacts like a NUL byte or a form feed.
+    ///
+    /// When set, such a character ends the token before it, and is then returned as
+    /// [`TokenizeError::InvalidChar`] from [`Tokenizer::try_next_token`], moving the cursor past it.
+    /// Tabs and line breaks are whitespace, so they are always allowed. By default, control
+    /// characters are kept in tokens like any other character.
+    pub fn reject_control_chars(mut self, reject_control_chars: bool) -> Self {
+        self.reject_control_chars = reject_control_chars;
+        self
+    }
+
+    /// Add operators to split tokens on, on top of the [`special_chars`], like `->` or `::`.
+    /// At each position the longest matching operator wins, whether it is a default or added one,
+    /// so adding `->` makes `a->b` three tokens instead of `a`, `-`, `>b`.
+    ///
+    /// Operators must not be empty, or contain whitespace or word characters, or
+    /// [`TokenizeError::InvalidOperator`] is returned. Operators split tokens wherever
+    /// they appear, so a word operator like `in` would split `print` into `pr`, `in`, `t`.
+    /// Word operators are already separate tokens when surrounded by whitespace.
+    pub fn operators(mut self, operators: Vec<String>) -> Result<Self, TokenizeError> {
+        if let Some(operator) = operators.iter().find(|operator| {
+            operator.is_empty()
+                || operator
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_alphanumeric() || c == '_')
+        }) {
+            return Err(TokenizeError::InvalidOperator {
+                operator: operator.clone(),
+            });
+        }
+        self.operators.extend(operators);
+        Ok(self)
+    }
+
+    /// The longest special string or added operator at the start of the input.
+    fn special_length(&self, rest: &str) -> Option<usize> {
+        let special: Option<usize> = SPECIAL_CHARS
+            .iter()
+            .find(|c| rest.starts_with(*c))
+            .map(|c| c.len());
+        let operator: Option<usize> = self
+            .operators
+            .iter()
+            .filter(|operator| rest.starts_with(operator.as_str()))
+            .map(String::len)
+            .max();
+        special.max(operator)
+    }
+
+    /// The problems recovered from so far in lenient mode.
+    pub fn diagnostics(&self) -> &[TokenizeError] {
+        &self.diagnostics
+    }
+
+    /// The input being tokenized.
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// The byte offset of the cursor.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// An empty span at the cursor.
+    pub fn cursor_span(&self) -> Span {
+        Span {
+            start: self.position,
+            end: self.position,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// Move the cursor to the given byte offset.
+    /// Tokenizing resumes from there, even if [`Tokenizer::next_token`] had stopped at an error.
+    /// Spans are still relative to the start of the input.
+    /// Seeking into a shebang line will tokenize the rest of the line.
+    ///
+    /// # Panics
+    /// Panics if the offset is not on a char boundary of the input.
+    pub fn seek(&mut self, position: usize) {
+        assert!(
+            self.input.is_char_boundary(position),
+            "Position {position} is not on a char boundary"
+        );
+        self.stopped = false;
+        // Lines and columns are counted from the cursor when moving forward,
+        // so tokenizing a file by seeking from token to token stays linear.
+        if position < self.position {
+            self.position = 0;
+            self.line = 1;
+            self.column = 1;
+            // A byte order mark isn't part of the text, like in `Tokenizer::new`.
+            if self.input.starts_with('\u{FEFF}') && position > 0 {
+                self.position = '\u{FEFF}'.len_utf8();
+            }
+        }
+        self.advance(position - self.position);
+    }
+
+    /// Get the next token, advancing the cursor past it.
+    /// Special characters will be returned as tokens. However, whitespace will not.
+    /// String literals and backtick-quoted identifiers, including their quotes,
+    /// are returned as a single token.
+    /// Comments are returned as tokens too: `// ...` up to the end of the line,
+    /// and `/* ... */` up to the closing `*/` (or the end of the input, if it is missing).
+    ///
+    /// Returns `None` at a token longer than the maximum token length,
+    /// or at a control character if [`Tokenizer::reject_control_chars`] is set.
+    /// After an error, this keeps returning `None`, so the [`Iterator`] impl is fused,
+    /// and tokens after the error are never mistaken for a complete input.
+    /// Use [`Tokenizer::try_next_token`] to get the error instead, and continue past it.
+    pub fn next_token(&mut self) -> Option<Token> {
+        if self.stopped {
+            return None;
+        }
+        self.try_next_token().unwrap_or_else(|_| {
+            self.stopped = true;
+            None
+        })
+    }
+
+    /// Get the next token, like [`Tokenizer::next_token`].
+    /// If the token is longer than the maximum token length, an error is returned,
+    /// and the cursor is moved past the token so tokenizing can continue.
+    /// Likewise, with [`Tokenizer::reject_control_chars`], a control character is an error,
+    /// and the cursor is moved past it.
+    pub fn try_next_token(&mut self) -> Result<Option<Token>, TokenizeError> {
+        self.try_next_borrowed()
+            .map(|token| token.map(BorrowedToken::into_owned))
+    }
+
+    /// Get the next token, like [`Tokenizer::try_next_token`], but borrowing its text from the
+    /// input instead of allocating, where possible.
+    pub fn try_next_borrowed(&mut self) -> Result<Option<BorrowedToken<'a>>, TokenizeError> {
+        let mut start: Option<Span> = None;
+
+        while self.position < self.input.len() {
+            let rest: &str = &self.input[self.position..];
+
+            if let Some(length) = comment_length(rest) {
+                if let Some(start) = start {
+                    return self.token(start).map(Some);
+                }
+                let start: Span = self.cursor_span();
+                self.advance(length);
+                return self.token(start).map(Some);
+            }
+
+            if let Some(quote) = rest.chars().next().filter(|c| matches!(c, '"' | '`')) {
+                if let Some(start) = start {
+                    return self.token(start).map(Some);
+                }
+                let start: Span = self.cursor_span();
+                let Some(length) = string_length(&rest[1..], quote) else {
+                    self.advance(rest.len());
+                    return self.unterminated(start, quote).map(Some);
+                };
+                self.advance(length + 1);
+                return self.token(start).map(Some);
+            }
+
+            if rest.starts_with('.') && self.is_numeric_dot(start) {
+                start.get_or_insert(self.cursor_span());
+                self.advance(1);
+                continue;
+            }
+
+            if rest.starts_with(['-', '+']) && self.is_exponent_sign(start) {
+                self.advance(1);
+                continue;
+            }
+
+            if let Some(length) = coordinate_length(rest) {
+                if let Some(start) = start {
+                    return self.token(start).map(Some);
+                }
+                let start: Span = self.cursor_span();
+                self.advance(length);
+                return self.token(start).map(Some);
+            }
+
+            if let Some(length) = self.special_length(rest) {
+                if let Some(start) = start {
+                    return self.token(start).map(Some);
+                }
+                let start: Span = self.cursor_span();
+                let special_char: &str = &rest[..length];
+                self.advance(length);
+                if !special_char.trim().is_empty() || (self.newlines && special_char == "\n") {
+                    return self.token(start).map(Some);
+                }
+                continue;
+            }
+
+            let ch: char = rest.chars().next().unwrap();
+            if self.reject_control_chars && ch.is_control() {
+                if let Some(start) = start {
+                    return self.token(start).map(Some);
+                }
+                let position: usize = self.position;
+                self.advance(ch.len_utf8());
+                return Err(TokenizeError::InvalidChar { ch, position });
+            }
+
+            start.get_or_insert(self.cursor_span());
+            self.advance(ch.len_utf8());
+        }
+
+        start.map(|start| self.token(start)).transpose()
+    }
+
+    /// Move the cursor forward by the given number of bytes, keeping track of lines and columns.
+    fn advance(&mut self, length: usize) {
+        let end: usize = self.position + length;
+        for (i, ch) in self.input[self.position..end].char_indices() {
+            let lone_cr: bool =
+                ch == '\r' && !self.input[self.position + i + 1..].starts_with('\n');
+            match ch {
+                '\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                }
+                '\r' if lone_cr => {
+                    self.line += 1;
+                    self.column = 1;
+                }
+                '\t' => self.column = ((self.column - 1) / self.tab_width + 1) * self.tab_width + 1,
+                _ => self.column += 1,
+            }
+        }
+        self.position = end;
+    }
+
+    /// Whether the `.` at the cursor is part of a number, like in `123.0`, `.5`, or `a:1.5`.
+    /// Such dots are kept in the token instead of being split off.
+    /// Numbers can follow a `:` in the same token, for NBT-style `key:value` pairs.
+    fn is_numeric_dot(&self, start: Option<Span>) -> bool {
+        let token: &str = start.map_or("", |start| &self.input[start.start..self.position]);
+        let number: &str = token.rsplit(':').next().unwrap();
+        if number.is_empty() {
+            self.input[self.position + 1..].starts_with(|c: char| c.is_ascii_digit())
+        } else {
+            number.starts_with(|c: char| c.is_ascii_digit())
+        }
+    }
+
+    /// Whether the `-` or `+` at the cursor is the sign of an exponent, like in `1.5e-3`,
+    /// so it should stay in the current number instead of being a token on its own.
+    fn is_exponent_sign(&self, start: Option<Span>) -> bool {
+        let Some(start) = start else {
+            return false;
+        };
+        let token: &str = &self.input[start.start..self.position];
+        let number: &str = token.rsplit(':').next().unwrap();
+        let Some(mantissa) = number.strip_suffix(['e', 'E']) else {
+            return false;
+        };
+        mantissa.starts_with(|c: char| c.is_ascii_digit())
+            && mantissa.chars().all(|c| c.is_ascii_digit() || c == '.')
+            && self.input[self.position + 1..].starts_with(|c: char| c.is_ascii_digit())
+    }
+
+    /// An unterminated string or quoted identifier from the given start up to the cursor.
+    /// In lenient mode, the closing quote is added and a diagnostic is recorded.
+    /// If the text ends in an unpaired backslash, like `"C:\`, it is doubled first,
+    /// so the added quote isn't escaped by it.
+    fn unterminated(
+        &mut self,
+        start: Span,
+        quote: char,
+    ) -> Result<BorrowedToken<'a>, TokenizeError> {
+        let mut token: BorrowedToken<'a> = self.token(start)?;
+        if self.lenient {
+            let backslashes: usize = token.text.chars().rev().take_while(|&c| c == '\\').count();
+            if backslashes % 2 == 1 {
+                token.text.to_mut().push('\\');
+            }
+            token.text.to_mut().push(quote);
+            self.diagnostics
+                .push(TokenizeError::UnterminatedString { span: token.span });
+        }
+        Ok(token)
+    }
+
+    /// A token from the given start up to the cursor.
+    /// The length is checked before the token is made.
+    fn token(&self, start: Span) -> Result<BorrowedToken<'a>, TokenizeError> {
+        let span: Span = Span {
+            end: self.position,
+            ..start
+        };
+        if let Some(max_length) = self.max_token_length {
+            if span.end - span.start > max_length {
+                return Err(TokenizeError::TokenTooLong { span, max_length });
+            }
+        }
+        Ok(BorrowedToken {
+            text: Cow::Borrowed(&self.input[span.start..span.end]),
+            span,
+        })
+    }
+}
+impl Iterator for Tokenizer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token()
+    }
+}
+impl FusedIterator for Tokenizer<'_> {}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum TokenizeError {
+    #[error("Indentation mixes tabs and spaces on line {line}")]
+    MixedIndentation { line: usize },
+
+    #[error("Token longer than {max_length} bytes at line {}, column {}", .span.line, .span.column)]
+    TokenTooLong { span: Span, max_length: usize },
+
+    #[error("Unterminated string at line {}, column {}", .span.line, .span.column)]
+    UnterminatedString { span: Span },
+
+    #[error("Invalid operator {operator:?}, operators must not be empty or contain whitespace or word characters")]
+    InvalidOperator { operator: String },
+
+    /// A control character outside of strings and comments,
+    /// with [`Tokenizer::reject_control_chars`] set.
+    /// `position` is its byte offset in the input.
+    #[error("Invalid character {ch:?} at byte {position}")]
+    InvalidChar { ch: char, position: usize },
+}
+
+/// Check that indentation doesn't mix tabs and spaces, for indentation-significant grammars.
+/// Each line must be indented with only tabs or only spaces, and all lines must use the same
+/// character as the first indented line. Lines containing only whitespace are ignored.
+pub fn check_indentation(input: &str) -> Result<(), TokenizeError> {
+    let mut indent_char: Option<char> = None;
+
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let indentation: &str = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        let Some(first) = indentation.chars().next() else {
+            continue;
+        };
+        if indentation.chars().any(|c| c != first) || *indent_char.get_or_insert(first) != first {
+            return Err(TokenizeError::MixedIndentation { line: i + 1 });
+        }
+    }
+
+    Ok(())
+}
+
+/// Split a string into tokens in lenient mode, returning the problems recovered from too.
+/// See [`Tokenizer::lenient`] for how problems are recovered from.
+pub fn tokenize_lenient(input: &str) -> (Vec<String>, Vec<TokenizeError>) {
+    let mut tokenizer: Tokenizer = Tokenizer::new(input).lenient(true);
+    let tokens: Vec<String> = tokenizer
+        .by_ref()
+        .filter(|token| !token.kind().is_comment())
+        .map(|token| token.text)
+        .collect();
+    (tokens, tokenizer.diagnostics)
+}
+
+/// Split a string into tokens, like [`tokenize`], but borrowing them from the input.
+/// Only tokens whose text isn't in the input are allocated, so this never allocates per token.
+pub fn tokenize_borrowed(input: &str) -> Vec<Cow<'_, str>> {
+    let mut tokenizer: Tokenizer = Tokenizer::new(input);
+    std::iter::from_fn(|| tokenizer.try_next_borrowed().ok().flatten())
+        .filter(|token| !token.kind().is_comment())
+        .map(|token| token.text)
+        .collect()
+}
+
 /// Split a string into tokens.
 /// Special characters will be included in tokens. However, whitespace will not.
+/// String literals are kept as single tokens, so whitespace inside them is preserved.
+/// A shebang line (`#!...`) at the very start of the input is skipped, and comments are removed.
 pub fn tokenize(input: &str) -> Vec<String> {
-    let mut tokens: Vec<String> = vec![];
-    let mut current_token: String = "".to_string();
+    Tokenizer::new(input)
+        .filter(|token| !token.kind().is_comment())
+        .map(|token| token.text)
+        .collect()
+}
 
-    let mut i: usize = 0;
-    while i < input.len() {
-        let ch: char = input.as_bytes()[i] as char;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let mut found_special_char: bool = false;
-        for special_char in SPECIAL_CHARS {
-            if input[i..].starts_with(special_char) {
-                found_special_char = true;
-                if !current_token.is_empty() {
-                    tokens.push(current_token);
-                    current_token = "".to_string();
+    #[test]
+    fn test_tokenizer() {
+        let mut tokenizer: Tokenizer = Tokenizer::new("let x = 5;");
+
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token {
+                text: "let".to_string(),
+                span: Span {
+                    start: 0,
+                    end: 3,
+                    line: 1,
+                    column: 1
+                }
+            })
+        );
+        assert_eq!(tokenizer.position(), 3);
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token {
+                text: "x".to_string(),
+                span: Span {
+                    start: 4,
+                    end: 5,
+                    line: 1,
+                    column: 5
+                }
+            })
+        );
+
+        tokenizer.seek(8);
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token {
+                text: "5".to_string(),
+                span: Span {
+                    start: 8,
+                    end: 9,
+                    line: 1,
+                    column: 9
                 }
-                if !special_char.trim().is_empty() {
-                    tokens.push(special_char.to_string());
+            })
+        );
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token {
+                text: ";".to_string(),
+                span: Span {
+                    start: 9,
+                    end: 10,
+                    line: 1,
+                    column: 10
                 }
-                i += special_char.len() - 1;
-                break;
+            })
+        );
+        assert_eq!(tokenizer.next_token(), None);
+
+        tokenizer.seek(0);
+        assert_eq!(
+            tokenizer.map(|token| token.text).collect::<Vec<String>>(),
+            tokenize("let x = 5;")
+        );
+    }
+
+    #[test]
+    fn test_lines_and_columns() {
+        let input: &str = "\ta\n  \t b\r\nc";
+        let positions = |tokenizer: Tokenizer| -> Vec<(usize, usize)> {
+            tokenizer
+                .map(|token| (token.span.line, token.span.column))
+                .collect()
+        };
+
+        assert_eq!(
+            positions(Tokenizer::new(input)),
+            vec![(1, 2), (2, 5), (3, 1)]
+        );
+        assert_eq!(
+            positions(Tokenizer::new(input).tab_width(4)),
+            vec![(1, 5), (2, 6), (3, 1)]
+        );
+
+        let mut tokenizer: Tokenizer = Tokenizer::new(input).tab_width(4);
+        tokenizer.seek(input.find('b').unwrap());
+        assert_eq!(positions(tokenizer.clone()), vec![(2, 6), (3, 1)]);
+
+        let line_and_column = |tokenizer: &mut Tokenizer| {
+            tokenizer
+                .next_token()
+                .map(|token| (token.span.line, token.span.column))
+        };
+        tokenizer.seek(input.find('c').unwrap());
+        assert_eq!(line_and_column(&mut tokenizer), Some((3, 1)));
+        tokenizer.seek(input.find('b').unwrap());
+        assert_eq!(line_and_column(&mut tokenizer), Some((2, 6)));
+        tokenizer.seek(input.find('c').unwrap());
+        assert_eq!(line_and_column(&mut tokenizer), Some((3, 1)));
+
+        let mut tokenizer: Tokenizer = Tokenizer::new("\u{FEFF}a b");
+        tokenizer.seek(5);
+        tokenizer.seek(3);
+        assert_eq!(line_and_column(&mut tokenizer), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_token() {
+        let input: &str = "name == \"value\"";
+        let tokens: Vec<Token> = Tokenizer::new(input).collect();
+
+        assert_eq!(tokens[1].text(), "==");
+        assert_eq!(
+            tokens[1].span(),
+            Span {
+                start: 5,
+                end: 7,
+                line: 1,
+                column: 6
             }
+        );
+        assert_eq!(tokens[1].byte_range(), 5..7);
+        assert_eq!(tokens[1].len(), 2);
+        assert!(!tokens[1].is_empty());
+        for token in tokens {
+            assert_eq!(&input[token.byte_range()], token.text());
         }
+    }
+
+    #[test]
+    fn test_escape_token() {
+        assert_eq!(escape_token("\n"), r"\n");
+        assert_eq!(escape_token("\r\t"), r"\r\t");
+        assert_eq!(escape_token(" "), r"\s");
+        assert_eq!(escape_token("\u{7}"), r"\u{7}");
+        assert_eq!(escape_token("\"a b\""), "\"a b\"");
+        assert_eq!(escape_token("name"), "name");
 
-        if !found_special_char {
-            current_token.push(ch);
+        let tokens: Vec<String> = Tokenizer::new("a\n==")
+            .newlines(true)
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(tokens, vec!["a", r"\n", "=="]);
+    }
+
+    #[test]
+    fn test_comments() {
+        let tokens: Vec<String> = Tokenizer::new("a // line\r\nb /* block\n */c/* open")
+            .map(|token| token.text)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec!["a", "// line", "b", "/* block\n */", "c", "/* open"]
+        );
+        assert_eq!(tokenize("a // b\nc/**/d"), vec!["a", "c", "d"]);
+        assert_eq!(tokenize("a/b \"//\""), vec!["a/b", "\"//\""]);
+        assert_eq!(
+            Tokenizer::new("a//b")
+                .map(|token| token.text)
+                .collect::<Vec<String>>(),
+            vec!["a", "//b"]
+        );
+    }
+
+    #[test]
+    fn test_token_kind() {
+        for (text, kind) in [
+            ("// comment", TokenKind::Comment),
+            ("/* comment */", TokenKind::Comment),
+            ("//// divider", TokenKind::Comment),
+            ("/// docs", TokenKind::DocComment),
+            ("\n", TokenKind::Newline),
+            ("\"string\"", TokenKind::String),
+            ("12", TokenKind::Number),
+            (".5", TokenKind::Number),
+            ("0xFF", TokenKind::Number),
+            ("==", TokenKind::Special),
+            ("~1", TokenKind::Special),
+            ("name", TokenKind::Word),
+            ("`quoted name`", TokenKind::Word),
+            (".", TokenKind::Special),
+        ] {
+            assert_eq!(TokenKind::of(text), kind, "{text}");
         }
+        assert!(TokenKind::DocComment.is_comment());
+        assert!(!TokenKind::Newline.is_comment());
+    }
+
+    #[test]
+    fn test_assemble_doc() {
+        let tokens: Vec<Token> =
+            Tokenizer::new("///\n/// A point.\n// not docs\n///\n///   x: 1  \n/// y\n///\nstruct")
+                .collect();
+        assert_eq!(
+            assemble_doc(&tokens),
+            Some("A point.\n\n  x: 1\ny".to_string())
+        );
 
-        i += 1;
+        let tokens: Vec<Token> = Tokenizer::new("///a\n///\tb").collect();
+        assert_eq!(assemble_doc(&tokens), Some("a\n\tb".to_string()));
+        let tokens: Vec<Token> = Tokenizer::new("///\n///").collect();
+        assert_eq!(assemble_doc(&tokens), Some(String::new()));
+        let tokens: Vec<Token> = Tokenizer::new("// a\n//// b\nc").collect();
+        assert_eq!(assemble_doc(&tokens), None);
     }
 
-    if !current_token.is_empty() {
-        tokens.push(current_token);
+    #[test]
+    fn test_special_chars() {
+        for special_char in special_chars() {
+            let expected: Vec<&str> = if special_char.trim().is_empty() {
+                vec!["a", "b"]
+            } else {
+                vec!["a", special_char, "b"]
+            };
+            assert_eq!(tokenize(&format!("a{special_char}b")), expected);
+        }
     }
 
-    tokens
+    #[test]
+    fn test_stars() {
+        assert_eq!(tokenize("a*b"), vec!["a", "*", "b"]);
+        assert_eq!(tokenize("a**b"), vec!["a", "**", "b"]);
+        assert_eq!(tokenize("a***b"), vec!["a", "**", "*", "b"]);
+        assert_eq!(tokenize("a****b"), vec!["a", "**", "**", "b"]);
+        assert_eq!(tokenize("a * * b"), vec!["a", "*", "*", "b"]);
+        assert_eq!(tokenize("!a != !!b"), vec!["!", "a", "!=", "!", "!", "b"]);
+    }
+
+    #[test]
+    fn test_assert_tokens() {
+        assert_tokens!("a==b", ["a", "==", "b"]);
+        assert_tokens!("a=b", ["a", "=", "b"]);
+        assert_tokens!("a===b", ["a", "==", "=", "b"]);
+        assert_tokens!("a!=!b", ["a", "!=", "!", "b"]);
+        assert_tokens!("a|||b", ["a", "||", "|", "b"]);
+        assert_tokens!("a&&b&c", ["a", "&&", "b&c"]);
+        assert_tokens!("a-1", ["a", "-", "1"]);
+        assert_tokens!("a.b 1.5 a:1.5f", ["a", ".", "b", "1.5", "a:1.5f"]);
+        assert_tokens!("~ ~-1 ^2.5", ["~", "~-1", "^2.5"]);
+        assert_tokens!("@e[type=pig]", ["@", "e", "[", "type", "=", "pig", "]"]);
+        assert_tokens!("#tag;", ["#", "tag", ";"]);
+        assert_tokens!("f(a,b)", ["f", "(", "a", ",", "b", ")"]);
+        assert_tokens!("x = \"a b\" // c", ["x", "=", "\"a b\""]);
+        assert_tokens!("1e-5 1.5E+3 x:2e-1", ["1e-5", "1.5E+3", "x:2e-1"]);
+        assert_tokens!(
+            "1e - 5 e-5 1e-x 0x1e-5",
+            ["1e", "-", "5", "e", "-", "5", "1e", "-", "x", "0x1e", "-", "5"]
+        );
+        assert_tokens!("", []);
+    }
+
+    #[test]
+    fn test_token_diff() {
+        let found: Vec<String> = tokenize("a = c");
+        assert_eq!(token_diff(&found, &["a", "=", "c"]), None);
+        assert_eq!(
+            token_diff(&found, &["a", "==", "c"]),
+            Some(
+                "first difference at index 1: expected `==`, found `=`\n\
+                 expected: [a, ==, c]\n   found: [a, =, c]"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            token_diff(&found, &["a", "="]).map(|diff| diff.lines().next().unwrap().to_string()),
+            Some("first difference at index 2: expected end of input, found `c`".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens of \"a b\" don't match")]
+    fn test_assert_tokens_mismatch() {
+        assert_tokens!("a b", ["a"]);
+    }
+
+    #[test]
+    fn test_operators() -> Result<(), TokenizeError> {
+        let texts =
+            |tokenizer: Tokenizer| -> Vec<String> { tokenizer.map(|token| token.text).collect() };
+        let operators: Vec<String> = vec!["->".to_string(), "::".to_string(), "===".to_string()];
+
+        assert_eq!(
+            texts(Tokenizer::new("a->b::c===d==e").operators(operators)?),
+            vec!["a", "->", "b", "::", "c", "===", "d", "==", "e"]
+        );
+        assert_eq!(
+            texts(Tokenizer::new("a->b").operators(vec!["-".to_string()])?),
+            vec!["a", "-", ">b"]
+        );
+        assert_eq!(
+            texts(Tokenizer::new("a<=>b").operators(vec!["<".to_string(), "<=>".to_string()])?),
+            vec!["a", "<=>", "b"]
+        );
+
+        for operator in ["", "a b", "\t", "in", "!in", "_"] {
+            assert_eq!(
+                Tokenizer::new("")
+                    .operators(vec![operator.to_string()])
+                    .err(),
+                Some(TokenizeError::InvalidOperator {
+                    operator: operator.to_string()
+                })
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let input: &str = "let x = \"a b\"; // c";
+        let borrowed: Vec<Cow<str>> = tokenize_borrowed(input);
+        assert_eq!(borrowed, tokenize(input));
+        assert!(borrowed
+            .iter()
+            .all(|token| matches!(token, Cow::Borrowed(_))));
+
+        let mut tokenizer: Tokenizer = Tokenizer::new("x \"open").lenient(true);
+        let x: BorrowedToken = tokenizer.try_next_borrowed().unwrap().unwrap();
+        assert_eq!(x.text, Cow::Borrowed("x"));
+        let open: BorrowedToken = tokenizer.try_next_borrowed().unwrap().unwrap();
+        assert_eq!(open.text, Cow::<str>::Owned("\"open\"".to_string()));
+        assert_eq!(
+            open.into_owned(),
+            Token {
+                text: "\"open\"".to_string(),
+                span: Span {
+                    start: 2,
+                    end: 7,
+                    line: 1,
+                    column: 3
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_tokens_macro() {
+        let tokens: Vec<String> = tokens!["a", "==", "b"];
+        assert_eq!(tokens, vec!["b", "==", "a"]);
+
+        let tokenized: Vec<String> = crate::tokenize!("a == b");
+        assert_eq!(tokens, tokenized);
+
+        let empty: Vec<String> = tokens![];
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(
+            tokenize("say \"hello world\";"),
+            vec!["say", "\"hello world\"", ";"]
+        );
+        assert_eq!(tokenize("a\"b\"c"), vec!["a", "\"b\"", "c"]);
+        assert_eq!(tokenize("\"\" \"unterminated"), vec!["\"\"", "\"unterminated"]);
+        assert_eq!(
+            tokenize(r#""a \"quote\"" "\\" "\"#),
+            vec![r#""a \"quote\"""#, r#""\\""#, r#""\"#]
+        );
+        assert_eq!(
+            tokenize(r"`a b`.`c\`d` `e"),
+            vec!["`a b`", ".", r"`c\`d`", "`e"]
+        );
+    }
+
+    #[test]
+    fn test_lenient() {
+        use crate::parse::{Literal, Parse};
+
+        let (tokens, diagnostics) = tokenize_lenient("say \"hello wor");
+        assert_eq!(tokens, vec!["say", "\"hello wor\""]);
+        assert_eq!(
+            diagnostics,
+            vec![TokenizeError::UnterminatedString {
+                span: Span {
+                    start: 4,
+                    end: 14,
+                    line: 1,
+                    column: 5
+                }
+            }]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "Unterminated string at line 1, column 5"
+        );
+
+        let (tokens, diagnostics) = tokenize_lenient("`a b");
+        assert_eq!(tokens, vec!["`a b`"]);
+        assert_eq!(diagnostics.len(), 1);
+
+        let (tokens, diagnostics) = tokenize_lenient("\"C:\\");
+        assert_eq!(tokens, vec!["\"C:\\\\\""]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            Literal::parse(&mut tokens.clone()),
+            Ok(Literal::String("C:\\".to_string()))
+        );
+        let (tokens, _) = tokenize_lenient("\"a\\\\");
+        assert_eq!(tokens, vec!["\"a\\\\\""]);
+
+        let (tokens, diagnostics) = tokenize_lenient("\"done\"");
+        assert_eq!(tokens, vec!["\"done\""]);
+        assert!(diagnostics.is_empty());
+
+        let mut tokenizer: Tokenizer = Tokenizer::new("\"open");
+        assert_eq!(tokenizer.next_token().unwrap().text, "\"open");
+        assert!(tokenizer.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_bom_and_line_endings() {
+        assert_eq!(tokenize("\u{FEFF}let x"), vec!["let", "x"]);
+        assert_eq!(tokenize("\u{FEFF}#!shebang\nx"), vec!["x"]);
+        assert_eq!(tokenize("a\u{FEFF}"), vec!["a\u{FEFF}"]);
+
+        let tokens: Vec<Token> = Tokenizer::new("\u{FEFF}a").collect();
+        assert_eq!(
+            tokens[0].span,
+            Span {
+                start: 3,
+                end: 4,
+                line: 1,
+                column: 1
+            }
+        );
+
+        let positions = |input: &str| -> Vec<(usize, usize)> {
+            Tokenizer::new(input)
+                .map(|token| (token.span.line, token.span.column))
+                .collect()
+        };
+        assert_eq!(positions("a\rb\r\rc"), vec![(1, 1), (2, 1), (4, 1)]);
+        assert_eq!(positions("a\r\nb\nc"), vec![(1, 1), (2, 1), (3, 1)]);
+        assert_eq!(tokenize("a // b\rc"), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn test_shebang() {
+        assert_eq!(tokenize("#!/usr/bin/env spec\na b"), vec!["a", "b"]);
+        assert_eq!(tokenize("#!only a shebang"), Vec::<String>::new());
+        assert_eq!(tokenize("a\n#!b"), vec!["a", "#", "!", "b"]);
+
+        let tokens: Vec<Token> = Tokenizer::new("#!shebang\nx").collect();
+        assert_eq!((tokens[0].span.line, tokens[0].span.column), (2, 1));
+    }
+
+    #[test]
+    fn test_check_indentation() {
+        assert_eq!(check_indentation("a\n    b\n        c\n\t\n"), Ok(()));
+        assert_eq!(check_indentation("a\n\tb\n\t\tc"), Ok(()));
+        assert_eq!(
+            check_indentation("a\n\tb\n\t    c"),
+            Err(TokenizeError::MixedIndentation { line: 3 })
+        );
+        assert_eq!(
+            check_indentation("a\n  b\n\tc"),
+            Err(TokenizeError::MixedIndentation { line: 3 })
+        );
+    }
+
+    #[test]
+    fn test_reject_control_chars() {
+        let input: &str = "a\0b \u{C} \"\0\" // \u{C}\nc";
+        let mut tokenizer: Tokenizer = Tokenizer::new(input).reject_control_chars(true);
+        assert_eq!(tokenizer.try_next_token().unwrap().unwrap().text, "a");
+        let error: TokenizeError = tokenizer.try_next_token().unwrap_err();
+        assert_eq!(
+            error,
+            TokenizeError::InvalidChar {
+                ch: '\0',
+                position: 1
+            }
+        );
+        assert_eq!(error.to_string(), "Invalid character '\\0' at byte 1");
+        assert_eq!(tokenizer.try_next_token().unwrap().unwrap().text, "b");
+        assert_eq!(
+            tokenizer.try_next_token(),
+            Err(TokenizeError::InvalidChar {
+                ch: '\u{C}',
+                position: 4
+            })
+        );
+
+        // Control characters in strings and comments are still allowed.
+        let rest: Vec<String> = tokenizer.map(|token| token.text).collect();
+        assert_eq!(rest, vec!["\"\0\"", "// \u{C}", "c"]);
+
+        assert_eq!(tokenize("a\0b \u{C}"), vec!["a\0b", "\u{C}"]);
+    }
+
+    #[test]
+    fn test_max_token_length() {
+        let long: String = "a".repeat(10_000);
+        let input: String = format!("ok {long} \"{long}\" b");
+        let mut tokenizer: Tokenizer = Tokenizer::new(&input).max_token_length(16);
+
+        assert_eq!(tokenizer.try_next_token().unwrap().unwrap().text, "ok");
+        let error: TokenizeError = tokenizer.try_next_token().unwrap_err();
+        assert_eq!(
+            error,
+            TokenizeError::TokenTooLong {
+                span: Span {
+                    start: 3,
+                    end: 10_003,
+                    line: 1,
+                    column: 4
+                },
+                max_length: 16
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Token longer than 16 bytes at line 1, column 4"
+        );
+        assert!(tokenizer.try_next_token().is_err());
+        assert_eq!(tokenizer.try_next_token().unwrap().unwrap().text, "b");
+        assert_eq!(tokenizer.try_next_token(), Ok(None));
+
+        let mut tokenizer: Tokenizer = Tokenizer::new(&input).max_token_length(16);
+        assert_eq!(tokenizer.next_token().unwrap().text, "ok");
+        assert_eq!(tokenizer.next_token(), None);
+        assert_eq!(tokenizer.next_token(), None);
+        assert_eq!(tokenizer.by_ref().count(), 0);
+        tokenizer.seek(input.len() - 1);
+        assert_eq!(tokenizer.next_token().unwrap().text, "b");
+        assert_eq!(tokenize(&input).len(), 4);
+    }
+
+    #[test]
+    fn test_newlines() {
+        let tokens: Vec<String> = Tokenizer::new("a\n b\r\n\nc")
+            .newlines(true)
+            .map(|token| token.text)
+            .collect();
+        assert_eq!(tokens, vec!["a", "\n", "b", "\n", "\n", "c"]);
+    }
+
+    #[test]
+    fn test_coordinates() {
+        assert_eq!(
+            tokenize("~ ~1 ~-2 ^ ^+.5 ^3.25 ~x ~-"),
+            vec!["~", "~1", "~-2", "^", "^+.5", "^3.25", "~", "x", "~", "-"]
+        );
+        assert_eq!(tokenize("~5~-3^"), vec!["~5", "~-3", "^"]);
+        assert_eq!(tokenize("a~1"), vec!["a", "~1"]);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        for input in ["", "   ", "\n\n", " \t\r\n "] {
+            assert_eq!(tokenize(input), Vec::<String>::new(), "{input:?}");
+            assert_eq!(Tokenizer::new(input).next_token(), None, "{input:?}");
+        }
+        assert_eq!(
+            Tokenizer::new("\n\n").newlines(true).count(),
+            2,
+            "newline tokens are still kept when asked for"
+        );
+
+        for special_char in special_chars().iter().filter(|c| !c.trim().is_empty()) {
+            assert_eq!(tokenize(special_char), vec![special_char.to_string()]);
+            assert_eq!(
+                tokenize(&format!(" {special_char}\n")),
+                vec![special_char.to_string()]
+            );
+        }
+    }
+
+    #[test]
+    fn test_dots() {
+        assert_eq!(tokenize("a.b.c"), vec!["a", ".", "b", ".", "c"]);
+        assert_eq!(tokenize("123.0 .5 1.20.1"), vec!["123.0", ".5", "1.20.1"]);
+        assert_eq!(tokenize("a.5 x1.y"), vec!["a", ".5", "x1", ".", "y"]);
+        assert_eq!(
+            tokenize("a:1.5f b:.5 c:d.e"),
+            vec!["a:1.5f", "b:.5", "c:d", ".", "e"]
+        );
+    }
+
+    /// A corpus of tricky inputs, from real spec snippets and adversarial cases.
+    /// This pins down current behavior, including quirks like `'` not starting a string,
+    /// so that changes to the tokenizer don't silently change how existing specs tokenize.
+    #[test]
+    fn test_corpus() {
+        let corpus: &[(&str, &[&str])] = &[
+            // Real spec snippets.
+            (
+                "packet Handshake { VarInt protocol; String(255) address; u16 port; }",
+                &[
+                    "packet",
+                    "Handshake",
+                    "{",
+                    "VarInt",
+                    "protocol",
+                    ";",
+                    "String",
+                    "(",
+                    "255",
+                    ")",
+                    "address",
+                    ";",
+                    "u16",
+                    "port",
+                    ";",
+                    "}",
+                ],
+            ),
+            (
+                "enum State(VarInt) { Status = 1, Login = 2, }",
+                &[
+                    "enum", "State", "(", "VarInt", ")", "{", "Status", "=", "1", ",", "Login",
+                    "=", "2", ",", "}",
+                ],
+            ),
+            (
+                "if (state == Login && !compressed) { x = y ** 2; }",
+                &[
+                    "if",
+                    "(",
+                    "state",
+                    "==",
+                    "Login",
+                    "&&",
+                    "!",
+                    "compressed",
+                    ")",
+                    "{",
+                    "x",
+                    "=",
+                    "y",
+                    "**",
+                    "2",
+                    ";",
+                    "}",
+                ],
+            ),
+            (
+                "@since(1.20) i32 count;",
+                &["@", "since", "(", "1.20", ")", "i32", "count", ";"],
+            ),
+            (
+                "minecraft:stone[facing=north,lit=true]",
+                &[
+                    "minecraft:stone",
+                    "[",
+                    "facing",
+                    "=",
+                    "north",
+                    ",",
+                    "lit",
+                    "=",
+                    "true",
+                    "]",
+                ],
+            ),
+            ("tp @p ~-2 ^1.5 ~", &["tp", "@", "p", "~-2", "^1.5", "~"]),
+            ("color #FF8800;", &["color", "#", "FF8800", ";"]),
+            ("/// Docs\nfield; // trailing", &["field", ";"]),
+            // Quotes and escapes.
+            (
+                "\"a \\\"quoted\\\" string\"",
+                &["\"a \\\"quoted\\\" string\""],
+            ),
+            (
+                "\"it's\" 'single \"inner\"'",
+                &["\"it's\"", "'single", "\"inner\"", "'"],
+            ),
+            ("\"a\\\\\"b", &["\"a\\\\\"", "b"]),
+            // Adjacent operators are matched longest first.
+            ("a==!=b", &["a", "==", "!=", "b"]),
+            ("a===b", &["a", "==", "=", "b"]),
+            ("a!==b", &["a", "!=", "=", "b"]),
+            ("****", &["**", "**"]),
+            ("|||&&&", &["||", "|", "&&", "&"]),
+            // Mixed whitespace and line endings.
+            ("a\t\r\nb\r\rc", &["a", "b", "c"]),
+            // Multi-byte characters aren't special, and only ASCII whitespace splits tokens.
+            ("名前 = \"值\";", &["名前", "=", "\"值\"", ";"]),
+            ("é+è", &["é", "+", "è"]),
+            ("🦀.rs", &["🦀", ".", "rs"]),
+            ("x\u{a0}y", &["x\u{a0}y"]),
+            // Unterminated constructs run to the end of the input.
+            ("\"abc", &["\"abc"]),
+            ("a /* unterminated", &["a"]),
+            // Comments.
+            ("/* a */b/**/c", &["b", "c"]),
+            ("a//b\nc", &["a", "c"]),
+            // Numbers, including exponent signs and dots.
+            ("1e-5-2", &["1e-5", "-", "2"]),
+            ("1.5E+3e", &["1.5E+3e"]),
+            ("1..2", &["1..2"]),
+            ("x.5", &["x", ".5"]),
+            (".5", &[".5"]),
+            ("-0x1F", &["-", "0x1F"]),
+            ("a.b.c()", &["a", ".", "b", ".", "c", "(", ")"]),
+            ("50%", &["50", "%"]),
+            // Empty input, whitespace only, and a BOM followed by a shebang.
+            ("", &[]),
+            (" \t\n ", &[]),
+            ("\u{feff}#!/usr/bin/env spec\nx", &["x"]),
+        ];
+        for (input, expected) in corpus {
+            if let Some(diff) = token_diff(&tokenize(input), expected) {
+                panic!("Tokens of {input:?} don't match\n{diff}");
+            }
+        }
+    }
 }